@@ -5,4 +5,4 @@ use criterion::*;
 use bitboard::*;
 use engine::*;
 
-criterion_main!(bench_weak_solver);
+criterion_main!(bench_weak_solver, bench_strong_solver, bench_bitboard);
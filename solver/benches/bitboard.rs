@@ -3,6 +3,20 @@ use criterion::*;
 use c4solver::board::BitBoard;
 use std::hint::black_box;
 
+/// Illegal on purpose: Floating stones in the fourth column and a vertical four in the fifth
+/// column. Shared by the benches of the line detection and the heuristics
+const FLOATING_FOUR: &str =
+    "nnnpnnn
+    nnnnpnn
+    nnnnpnn
+    npnppnn
+    npnnpnn
+    nncccpn";
+
+fn floating_four() -> BitBoard {
+    BitBoard::from_string_unchecked(FLOATING_FOUR).unwrap()
+}
+
 pub fn bench_winning_move(crit: &mut Criterion) {
     let board_1 =
         "nnnpnnn
@@ -12,7 +26,8 @@ pub fn bench_winning_move(crit: &mut Criterion) {
         npnnpnn
         nncccpn";
 
-    let bits_1 = BitBoard::from_string(board_1).unwrap();
+    // Floating stones and too many stones of the player to move
+    let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
 
     let player_move =
         "nnnnnnn
@@ -21,56 +36,45 @@ pub fn bench_winning_move(crit: &mut Criterion) {
             nnnnnnn
             nnnnnnn
             nnnnnnn";
-    let position = BitBoard::from_string(player_move).unwrap().get_player_bit_repr();
+    let position = BitBoard::mask_from_string(player_move).unwrap();
 
     crit.bench_function("winning_move_01", |b| b.iter( || bits_1.is_winning_move(black_box(position))));
 }
 
 pub fn bench_winning_board(crit: &mut Criterion) {
-    let board_1 =
-        "nnnpnnn
-        nnnnpnn
-        nnnnpnn
-        npnppnn
-        npnnpnn
-        nncccpn";
-
-    let bits_1 = BitBoard::from_string(board_1).unwrap();
-    let bits_1 = black_box(bits_1);
+    let bits_1 = black_box(floating_four());
 
     crit.bench_function("winning_board_01", |b| b.iter( || bits_1.has_won()));
 
 }
 
 pub fn bench_evaluation(crit: &mut Criterion) {
-    let board_1 =
-        "nnnpnnn
-        nnnnpnn
-        nnnnpnn
-        npnppnn
-        npnnpnn
-        nncccpn";
-
-    let bits_1 = BitBoard::from_string(board_1).unwrap();
-    let bits_1 = black_box(bits_1);
+    let bits_1 = black_box(floating_four());
 
     crit.bench_function("evaluation_01", |b| b.iter(|| bits_1.heuristic()));
 }
 
 pub fn bench_heuristics2(crit: &mut Criterion) {
-    let board_1 =
-        "nnnpnnn
-        nnnnpnn
-        nnnnpnn
-        npnppnn
-        npnnpnn
-        nncccpn";
+    let bits_1 = black_box(floating_four());
 
+    crit.bench_function("heuristics2_01", |b| b.iter(|| bits_1.heuristic_2()));
+
+}
+
+pub fn bench_heuristic_parity(crit: &mut Criterion) {
+    let board_1 =
+        "nnpnnnc
+        nnpnccc
+        pncnccp
+        cppnppp
+        pcpncpp
+        ccpccpc";
+
+    // Quiet endgame without immediate wins or forced moves
     let bits_1 = BitBoard::from_string(board_1).unwrap();
     let bits_1 = black_box(bits_1);
 
-    crit.bench_function("heuristics2_01", |b| b.iter(|| bits_1.heuristic_2()));
-
+    crit.bench_function("heuristic_parity_01", |b| b.iter(|| bits_1.heuristic_parity()));
 }
 
-criterion_group!(bench_bitboard, bench_heuristics2, bench_evaluation, bench_winning_move, bench_winning_board);
\ No newline at end of file
+criterion_group!(bench_bitboard, bench_heuristics2, bench_evaluation, bench_heuristic_parity, bench_winning_move, bench_winning_board);
\ No newline at end of file
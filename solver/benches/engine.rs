@@ -2,6 +2,7 @@ use criterion::*;
 use c4solver::board::BitBoard;
 use std::hint::black_box;
 use c4solver::engine::*;
+use c4solver::positions::PositionGenerator;
 
 const EARLY_01: &str =
            "nnnnnnn
@@ -43,7 +44,8 @@ pub fn bench_weak_early(crit: &mut Criterion) {
                                                                   i32::MAX-2, &mut num_nodes)));
 }
 pub fn bench_weak_mid(crit: &mut Criterion) {
-    let board_01 = black_box(BitBoard::from_string(MID_01).unwrap());
+    // Too many stones of the opponent
+    let board_01 = black_box(BitBoard::from_string_unchecked(MID_01).unwrap());
     let board_02 = black_box(BitBoard::from_string(MID_02).unwrap());
     let mut num_nodes: u64 = 0;
 
@@ -53,4 +55,58 @@ pub fn bench_weak_mid(crit: &mut Criterion) {
                                                                   i32::MAX-2, &mut num_nodes)));
 }
 
-criterion_group!(bench_weak_solver, bench_weak_early, bench_weak_mid);
+/// Solves a seeded corpus of random midgame positions, less tuned to single positions than
+/// the hand-written boards
+pub fn bench_weak_random(crit: &mut Criterion) {
+    let corpus: Vec<BitBoard> = black_box(PositionGenerator::new(1, 16).take(8).collect());
+    let mut num_nodes: u64 = 0;
+
+    crit.bench_function("weak_random_16", |b| b.iter(|| {
+        for board in &corpus {
+            solve_weak(*board, 11, i32::MIN+2, i32::MAX-2, &mut num_nodes);
+        }
+    }));
+}
+
+/// Null window searches of random positions with 18 stones, with and without trying the
+/// knowledge based rules at the root (see KNOWLEDGE_MIN_DEPTH)
+pub fn bench_weak_knowledge(crit: &mut Criterion) {
+    let corpus: Vec<BitBoard> = black_box(PositionGenerator::new(1, 18).take(8).collect());
+    let without = SearchOptions::default();
+    let with = SearchOptions { knowledge_cutoffs: true, ..SearchOptions::default() };
+    let mut num_nodes: u64 = 0;
+
+    let mut group = crit.benchmark_group("weak_knowledge");
+    group.sample_size(10);
+    group.bench_function("without_cutoffs", |b| b.iter(|| {
+        for board in &corpus {
+            solve_weak_with_options(*board, 24, 0, 1, &without, &mut num_nodes);
+        }
+    }));
+    group.bench_function("with_cutoffs", |b| b.iter(|| {
+        for board in &corpus {
+            solve_weak_with_options(*board, 24, 0, 1, &with, &mut num_nodes);
+        }
+    }));
+    group.finish();
+}
+
+pub fn bench_strong_early(crit: &mut Criterion) {
+    let board_01 = black_box(BitBoard::from_string(EARLY_01).unwrap());
+    let board_02 = black_box(BitBoard::from_string(EARLY_02).unwrap());
+    let full_depth = SearchOptions::default();
+    let reduced = SearchOptions { late_move_reductions: true, ..SearchOptions::default() };
+    let mut num_nodes: u64 = 0;
+
+    crit.bench_function("strong_early_01", |b| b.iter(|| solve_strong_with_options(board_01, 11,
+        i32::MIN+2, i32::MAX-2, &full_depth, &mut num_nodes)));
+    crit.bench_function("strong_early_01_lmr", |b| b.iter(|| solve_strong_with_options(board_01, 11,
+        i32::MIN+2, i32::MAX-2, &reduced, &mut num_nodes)));
+    crit.bench_function("strong_early_02", |b| b.iter(|| solve_strong_with_options(board_02, 11,
+        i32::MIN+2, i32::MAX-2, &full_depth, &mut num_nodes)));
+    crit.bench_function("strong_early_02_lmr", |b| b.iter(|| solve_strong_with_options(board_02, 11,
+        i32::MIN+2, i32::MAX-2, &reduced, &mut num_nodes)));
+}
+
+criterion_group!(bench_weak_solver, bench_weak_early, bench_weak_mid, bench_weak_random, bench_weak_knowledge);
+criterion_group!(bench_strong_solver, bench_strong_early);
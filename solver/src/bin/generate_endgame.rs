@@ -0,0 +1,44 @@
+//! Generates an endgame database for the web application
+//!
+//! Usage: generate_endgame <min stones> <output file> <root board>
+//!
+//! The root board uses the format of BitBoard::from_string, e.g. "nnnnnnn...". All positions
+//! with at least min stones that can be reached from it are generated. The root board is
+//! required, from the empty board there are far too many positions for any useful K.
+
+use c4solver::board::BitBoard;
+use c4solver::endgame::EndgameDatabase;
+use std::process::exit;
+use std::time::Instant;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        eprintln!("Usage: {} <min stones> <output file> <root board>", args[0]);
+        exit(1);
+    }
+
+    let min_stones: u8 = match args[1].parse() {
+        Ok(min_stones) if min_stones <= 42 => min_stones,
+        _ => {
+            eprintln!("The minimum number of stones has to be between 0 and 42");
+            exit(1);
+        }
+    };
+    let root = match BitBoard::from_string(&args[3]) {
+        Ok(root) => root,
+        Err(e) => {
+            eprintln!("Invalid root board: {}", e);
+            exit(1);
+        }
+    };
+
+    let start = Instant::now();
+    let database = EndgameDatabase::generate(&root, min_stones);
+    println!("Solved {} positions in {:?}", database.len(), start.elapsed());
+
+    if let Err(e) = std::fs::write(&args[2], database.to_bytes()) {
+        eprintln!("Could not write {}: {}", args[2], e);
+        exit(1);
+    }
+}
@@ -0,0 +1,60 @@
+//! Generates a corpus of random positions for tests and benchmarks
+//!
+//! Usage: generate_positions <seed> <ply> <count> [filter]
+//!
+//! Prints one position per line in the format of BitBoard::from_string without line breaks.
+//! The filter is one of first, second or draw for the outcome under perfect play, or
+//! forced=<n> for the number of forced moves of the player to move.
+
+use c4solver::game::Outcome;
+use c4solver::positions::{Filter, PositionGenerator};
+use std::process::exit;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 4 || args.len() > 5 {
+        eprintln!("Usage: {} <seed> <ply> <count> [first|second|draw|forced=<n>]", args[0]);
+        exit(1);
+    }
+
+    let seed: u64 = parse(&args[1], "The seed has to be a number");
+    let ply: u8 = match args[2].parse() {
+        Ok(ply) if ply < 42 => ply,
+        _ => {
+            eprintln!("The ply has to be between 0 and 41");
+            exit(1);
+        }
+    };
+    let count: usize = parse(&args[3], "The count has to be a number");
+    let filter = match args.get(4).map(String::as_str) {
+        None => Filter::Any,
+        Some("first") => Filter::Outcome(Outcome::FirstPlayerWins),
+        Some("second") => Filter::Outcome(Outcome::SecondPlayerWins),
+        Some("draw") => Filter::Outcome(Outcome::Draw),
+        Some(forced) if forced.starts_with("forced=") => {
+            Filter::ForcedMoves(parse(&forced["forced=".len()..], "The number of forced moves has to be a number"))
+        }
+        Some(filter) => {
+            eprintln!("Unknown filter {}", filter);
+            exit(1);
+        }
+    };
+
+    let mut generator = PositionGenerator::new(seed, ply).with_filter(filter);
+    for _ in 0..count {
+        match generator.next_position() {
+            Some(board) => println!("{}", board.to_string().replace('\n', "")),
+            None => {
+                eprintln!("No position found that passes the filter");
+                exit(1);
+            }
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(input: &str, error: &str) -> T {
+    input.parse().unwrap_or_else(|_| {
+        eprintln!("{}", error);
+        exit(1);
+    })
+}
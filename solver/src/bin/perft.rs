@@ -0,0 +1,52 @@
+//! Validates the move generation by counting the positions after every ply
+//!
+//! Usage: perft [max ply] [--unique]
+//!
+//! Prints the number of move sequences (perft) and of distinct positions after each ply up to
+//! max ply (default 8) and compares the distinct positions with OEIS A212693.
+//! With --unique, only the distinct positions are counted, which is much faster for
+//! later plies.
+
+use c4solver::board::BitBoard;
+use c4solver::perft::{perft, perft_unique, POSITIONS_PER_PLY};
+use std::process::exit;
+use std::time::Instant;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let unique_only = args.iter().any(|arg| arg == "--unique");
+    let numbers: Vec<&String> = args[1..].iter().filter(|arg| *arg != "--unique").collect();
+    if numbers.len() > 1 {
+        eprintln!("Usage: {} [max ply] [--unique]", args[0]);
+        exit(1);
+    }
+
+    let max_ply = match numbers.first().map(|ply| ply.parse::<usize>()) {
+        None => 8,
+        Some(Ok(ply)) if ply < POSITIONS_PER_PLY.len() => ply,
+        _ => {
+            eprintln!("The maximum ply has to be between 0 and {}", POSITIONS_PER_PLY.len() - 1);
+            exit(1);
+        }
+    };
+
+    let empty = BitBoard::empty();
+    let mut failed = false;
+    for (ply, expected) in POSITIONS_PER_PLY.iter().enumerate().take(max_ply + 1) {
+        let start = Instant::now();
+        let positions = perft_unique(&empty, ply as u8);
+        let sequences = if unique_only {
+            String::from("-")
+        } else {
+            perft(&empty, ply as u8).to_string()
+        };
+        let status = if positions == *expected { "ok" } else { "MISMATCH" };
+        println!("ply {:2}: {:>12} sequences, {:>10} positions (expected {:>10}) {} in {:?}",
+                 ply, sequences, positions, expected, status, start.elapsed());
+        failed |= positions != *expected;
+    }
+
+    if failed {
+        exit(1);
+    }
+}
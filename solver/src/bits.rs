@@ -0,0 +1,71 @@
+//! Storage of the bits of a board
+//!
+//! Boards up to 64 bits (WIDTH * (HEIGHT + 1) <= 64) are stored in a u64, larger boards
+//! like 9x7 or 10x8 in a u128. All board logic is written once against the Bits trait.
+
+use std::fmt;
+use std::hash::Hash;
+use std::ops::{Add, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr, Sub};
+
+/// An unsigned integer used as the bits of a board
+pub trait Bits: Copy + Eq + Ord + Hash + Default + fmt::Debug + fmt::LowerHex
+    + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self> + Not<Output = Self>
+    + Add<Output = Self> + Sub<Output = Self> + Shl<usize, Output = Self> + Shr<usize, Output = Self>
+    + BitAndAssign + BitOrAssign + BitXorAssign {
+    /// Number of available bits
+    const BITS: usize;
+    const ZERO: Self;
+    const ONE: Self;
+
+    /// Converts a mask, dropping the bits that don't fit
+    fn from_mask(mask: u128) -> Self;
+    fn to_mask(self) -> u128;
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+    fn leading_zeros(self) -> u32;
+    fn wrapping_neg(self) -> Self;
+}
+
+macro_rules! impl_bits {
+    ($($bits:ty),*) => {
+        $(
+            impl Bits for $bits {
+                const BITS: usize = <$bits>::BITS as usize;
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                #[inline(always)]
+                fn from_mask(mask: u128) -> Self {
+                    mask as $bits
+                }
+
+                #[inline(always)]
+                fn to_mask(self) -> u128 {
+                    self as u128
+                }
+
+                #[inline(always)]
+                fn count_ones(self) -> u32 {
+                    <$bits>::count_ones(self)
+                }
+
+                #[inline(always)]
+                fn trailing_zeros(self) -> u32 {
+                    <$bits>::trailing_zeros(self)
+                }
+
+                #[inline(always)]
+                fn leading_zeros(self) -> u32 {
+                    <$bits>::leading_zeros(self)
+                }
+
+                #[inline(always)]
+                fn wrapping_neg(self) -> Self {
+                    <$bits>::wrapping_neg(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_bits!(u64, u128);
@@ -1,4 +1,8 @@
-use std::thread::current;
+use crate::bits::Bits;
+use crate::endgame::DatabaseError;
+#[cfg(feature = "serde")]
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 use wasm_bindgen::prelude::*;
 
 
@@ -6,16 +10,36 @@ use wasm_bindgen::prelude::*;
 pub const BOARD_WIDTH: usize = 7;
 pub const BOARD_HEIGHT: usize = 6;
 
-/// Encoding: Column-first-order, 8-bit per column.
-/// That way 2 bits per column (they are 6 fields high) are left unused.
-#[wasm_bindgen]
+/// Encoding: Column-first-order, HEIGHT + 1 bits per column.
+/// That way 1 bit per column is left unused. Any size with WIDTH * (HEIGHT + 1) <= 64 fits
+/// into the default u64, larger boards up to 128 bits have to use u128.
+/// N is the number of stones in a row that win the game.
+/// On CYLINDRICAL boards the first column is the neighbour of the last one, so horizontal
+/// and diagonal lines may wrap around.
+/// In MISERE games completing a line loses instead of winning.
 #[derive(Default, Eq, PartialEq, Debug, Copy, Clone)]
-pub struct BitBoard {
-    player: u64,
-    occupied: u64,
+pub struct Board<const WIDTH: usize, const HEIGHT: usize, B = u64, const N: usize = 4,
+                 const CYLINDRICAL: bool = false, const MISERE: bool = false> {
+    player: B,
+    occupied: B,
+}
+
+/// The standard board with 7 columns and 6 rows
+pub type BitBoard = Board<BOARD_WIDTH, BOARD_HEIGHT>;
+
+/// Sets the lowest bit of every column
+const fn bottom_line(width: usize, column_bits: usize) -> u128 {
+    let mut line = 0;
+    let mut column = 0;
+    while column < width {
+        line |= 1 << (column * column_bits);
+        column += 1;
+    }
+    line
 }
 
 /// A typesafe wrapper around a u64 that represents a move
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct Move {
     move_repr: u64,
 }
@@ -31,23 +55,63 @@ impl Move {
     pub fn is_valid(&self) -> bool {
         let is_power_of_two = self.move_repr != 0 && (self.move_repr & (self.move_repr - 1)) == 0;
 
-        todo!("Check if the move is valid")
+        is_power_of_two && self.move_repr & BitBoard::PLAYABLE_FIELDS > 0
     }
 
+    /// Creates a move without checking it. Use try_new for bitmasks of unknown origin
     pub fn new(move_repr: u64) -> Move {
         Move {
             move_repr
         }
     }
 
-    /// Returns the index of the board that is affected by this move
-    pub fn get_board_index() -> BoardField {
-        todo!("Get the board index from the move")
+    /// Creates a move, if the bitmask represents a single field of the board
+    pub fn try_new(move_repr: u64) -> Result<Move, BoardError> {
+        let mov = Move::new(move_repr);
+        if mov.is_valid() {
+            Ok(mov)
+        } else {
+            Err(BoardError::InvalidField { bits: move_repr })
+        }
+    }
+
+    /// Creates the move on the given column and row, both starting at 0 in the bottom left
+    pub fn from_coordinates(column: u8, row: u8) -> Result<Move, BoardError> {
+        if column as usize >= BOARD_WIDTH {
+            return Err(BoardError::ColumnOutOfRange { column });
+        }
+        if row as usize >= BOARD_HEIGHT {
+            return Err(BoardError::RowOutOfRange { row });
+        }
+        Ok(Move::new(BitBoard::coordinate_to_field(column, row)))
+    }
+
+    pub fn from_field(field: &BoardField) -> Move {
+        Move::new(1 << field.get_bit_pos())
+    }
+
+    /// Column of the move, starting at 0 on the left.
+    /// Only meaningful for valid moves
+    pub fn column(&self) -> u8 {
+        (self.move_repr.trailing_zeros() as usize / BitBoard::COLUMN_BITS) as u8
+    }
+
+    /// Row of the move, starting at 0 at the bottom.
+    /// Only meaningful for valid moves
+    pub fn row(&self) -> u8 {
+        (self.move_repr.trailing_zeros() as usize % BitBoard::COLUMN_BITS) as u8
+    }
+
+    /// Returns the index of the board that is affected by this move.
+    /// Only meaningful for valid moves
+    pub fn get_board_index(&self) -> BoardField {
+        BoardField::from_coordinates(self.column(), self.row())
     }
 }
 
 /// A simple wrapper around a u8 index type to get to use type information when
 /// accessing the board fields
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct BoardField {
     index: u8,
 }
@@ -56,20 +120,33 @@ impl BoardField {
     /// Calculates the position of the bit in the internal u64 field used to represent the board
     /// that is used to store the information about this specific Field
     pub fn get_bit_pos(&self) -> u8 {
-        let row = self.index % 6;
-        let column = self.index / 6;
-        let bit_pos = column * 8 + row;
-        bit_pos
+        self.column() * BitBoard::COLUMN_BITS as u8 + self.row()
     }
 
     pub fn get_index(&self) -> u8 {
         self.index
     }
 
+    /// Field on the given column and row, both starting at 0 in the bottom left.
+    /// Coordinates outside of the board are not checked
+    pub fn from_coordinates(column: u8, row: u8) -> BoardField {
+        BoardField {
+            index: column * BOARD_HEIGHT as u8 + row
+        }
+    }
+
+    pub fn column(&self) -> u8 {
+        self.index / BOARD_HEIGHT as u8
+    }
+
+    pub fn row(&self) -> u8 {
+        self.index % BOARD_HEIGHT as u8
+    }
 }
 
 pub struct BoardIterator<'a> {
     current_index: u8,
+    #[allow(dead_code)]
     board: &'a BitBoard,
 }
 
@@ -78,9 +155,9 @@ impl<'a> Iterator for BoardIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.current_index < 42 {
-            Some(
-                BoardField{index: self.current_index}
-            )
+            let field = BoardField{index: self.current_index};
+            self.current_index += 1;
+            Some(field)
         } else {
             None
         }
@@ -88,11 +165,44 @@ impl<'a> Iterator for BoardIterator<'a> {
 
     /// The size of a connect four game-board is always 42
     fn size_hint(&self) -> (usize, Option<usize>) {
-        return ((42 - self.current_index) as usize, Some((42 - self.current_index) as usize))
+        ((42 - self.current_index) as usize, Some((42 - self.current_index) as usize))
     }
 }
 
 impl <'a> BitBoard {
+    /// Plays the move for the current player and changes the player.
+    /// Fails if the move is no field of the board or can not be played right now
+    pub fn play_move(&self, mov: Move) -> Result<BitBoard, BoardError> {
+        self.check_playable(mov)?;
+        Ok(self.play_field(mov.get_move_repr()))
+    }
+
+    /// Returns if the move would win the game for the current player.
+    /// Fails if the move is no field of the board or can not be played right now
+    pub fn is_winning(&self, mov: Move) -> Result<bool, BoardError> {
+        self.check_playable(mov)?;
+        Ok(self.is_winning_move(mov.get_move_repr()))
+    }
+
+    /// Same as set_at, but fails if the move is no field of the board
+    pub fn set_at_move(&mut self, mov: Move, field: Option<FieldType>) -> Result<(), BoardError> {
+        if !mov.is_valid() {
+            return Err(BoardError::InvalidField { bits: mov.get_move_repr() });
+        }
+        self.set_at(mov.get_move_repr(), field);
+        Ok(())
+    }
+
+    fn check_playable(&self, mov: Move) -> Result<(), BoardError> {
+        if !mov.is_valid() {
+            return Err(BoardError::InvalidField { bits: mov.get_move_repr() });
+        }
+        if mov.get_move_repr() & self.all_possible_moves() == 0 {
+            return Err(BoardError::FieldNotPlayable { column: mov.column(), row: mov.row() });
+        }
+        Ok(())
+    }
+
     pub fn field_iter(&'a self) -> BoardIterator<'a> {
         BoardIterator {
             current_index: 0,
@@ -110,13 +220,14 @@ impl <'a> BitBoard {
 
 }
 
-impl BitBoard {
+impl<const WIDTH: usize, const HEIGHT: usize, B: Bits, const N: usize, const CYLINDRICAL: bool, const MISERE: bool>
+    Board<WIDTH, HEIGHT, B, N, CYLINDRICAL, MISERE> {
 
-    pub fn get_player_bit_repr(&self) -> u64 {
+    pub fn get_player_bit_repr(&self) -> B {
         self.player
     }
 
-    pub fn get_opponent_bit_repr(&self) -> u64 {
+    pub fn get_opponent_bit_repr(&self) -> B {
         self.occupied - self.player
     }
 
@@ -124,23 +235,23 @@ impl BitBoard {
     pub fn number_of_stones(&self) -> u32 {
         self.occupied.count_ones()
     }
-    pub fn empty() -> BitBoard {
-        BitBoard::default()
+    pub fn empty() -> Self {
+        Self::default()
     }
     /// Plays a stone at the given position without swapping players.
     /// if player is true, the current player will be played.
     /// If false the opposing player will be played
     pub fn set_at_coordinate(&mut self, x: u8, y: u8, field: Option<FieldType>) {
-        let to_play = BitBoard::coordinate_to_field(x, y);
+        let to_play = Self::coordinate_to_field(x, y);
         self.set_at(to_play, field);
     }
 
-    pub fn set_at(&mut self, to_play: u64, field: Option<FieldType>) {
+    pub fn set_at(&mut self, to_play: B, field: Option<FieldType>) {
         match field {
             None => {
                 // Set field to zero for both players
-                self.player & (!to_play);
-                self.occupied & (!to_play);
+                self.player &= !to_play;
+                self.occupied &= !to_play;
             }
 
             Some(FieldType::Player) => {
@@ -155,10 +266,10 @@ impl BitBoard {
     }
 
     pub fn get_at(&self, x: u8, y: u8) -> Option<FieldType> {
-        let field = BitBoard::coordinate_to_field(x, y);
-        if (self.player & field) > 0 {
+        let field = Self::coordinate_to_field(x, y);
+        if (self.player & field) != B::ZERO {
             return Some(FieldType::Player);
-        } else if (self.occupied & field) > 0 {
+        } else if (self.occupied & field) != B::ZERO {
             return Some(FieldType::Opponent);
         }
 
@@ -166,9 +277,9 @@ impl BitBoard {
     }
 
     #[inline]
-    fn coordinate_to_field(x: u8, y: u8) -> u64 {
-        let pos = x * 8 + y;
-        1 << pos
+    fn coordinate_to_field(x: u8, y: u8) -> B {
+        let pos = x as usize * Self::COLUMN_BITS + y as usize;
+        B::ONE << pos
     }
 
     /// Switches the active player represented by the "player" bits
@@ -179,208 +290,486 @@ impl BitBoard {
         self.player = occupied - player;
     }
 
+    /// Renders the board for the terminal with column numbers below it.
+    /// X is the player to move and O the opponent. With colours, X is red and O yellow.
+    pub fn to_pretty_string(&self, colours: bool) -> String {
+        let (player, opponent) = if colours {
+            ("\x1b[31mX\x1b[0m", "\x1b[33mO\x1b[0m")
+        } else {
+            ("X", "O")
+        };
+
+        let mut output = String::new();
+        for row in (0..HEIGHT).rev() {
+            output.push('|');
+            for column in 0..WIDTH {
+                output.push(' ');
+                output.push_str(match self.get_at(column as u8, row as u8) {
+                    Some(FieldType::Player) => player,
+                    Some(FieldType::Opponent) => opponent,
+                    None => ".",
+                });
+            }
+            output.push_str(" |\n");
+        }
+        output.push(' ');
+        for column in 1..=WIDTH {
+            output.push_str(&format!(" {}", column));
+        }
+        output.push('\n');
+        output
+    }
+
+    /// Returns a unique key of the position, that fits in WIDTH * (HEIGHT + 1) bits (49 bits
+    /// for the standard board).
+    /// Each column is stored in its bits as player + occupied + bottom field, that is a one
+    /// above the highest stone and the stones of the player to move below it.
+    pub fn key(&self) -> B {
+        self.player + self.occupied + Self::bottom_line()
+    }
+
+    /// Restores the position from its key. The inverse of BitBoard::key().
+    /// The position has to be legal, see BitBoard::validate()
+    pub fn from_key(key: B) -> Result<Self, BoardError> {
+        if key & !Self::board_fields() != B::ZERO {
+            return Err(BoardError::InvalidKey { key: key.to_mask() });
+        }
+        let mut board = Self::empty();
+        for column in 0..WIDTH {
+            let column_key = (key >> (column * Self::COLUMN_BITS)) & Self::first_column();
+            if column_key == B::ZERO {
+                return Err(BoardError::InvalidKey { key: key.to_mask() });
+            }
+            // The highest bit marks the height of the column
+            let height = B::BITS - 1 - column_key.leading_zeros() as usize;
+            let occupied = (B::ONE << height) - B::ONE;
+            board.occupied |= occupied << (column * Self::COLUMN_BITS);
+            board.player |= (column_key & occupied) << (column * Self::COLUMN_BITS);
+        }
+        board.check_legal()
+    }
+
+    /// Returns the same position on the standard board, if this board has the standard size
+    /// and rules
+    pub fn as_standard(&self) -> Option<BitBoard> {
+        let standard = WIDTH == BOARD_WIDTH && HEIGHT == BOARD_HEIGHT && N == 4 && !CYLINDRICAL && !MISERE;
+        standard.then(|| Board {
+            player: self.player.to_mask() as u64,
+            occupied: self.occupied.to_mask() as u64,
+        })
+    }
+
+    /// Returns the same position, but M stones in a row win
+    pub fn with_line_length<const M: usize>(&self) -> Board<WIDTH, HEIGHT, B, M, CYLINDRICAL, MISERE> {
+        Board {
+            player: self.player,
+            occupied: self.occupied,
+        }
+    }
+
+    /// Returns the same position, but with misère rules if M is true
+    pub fn with_misere<const M: bool>(&self) -> Board<WIDTH, HEIGHT, B, N, CYLINDRICAL, M> {
+        Board {
+            player: self.player,
+            occupied: self.occupied,
+        }
+    }
+
+    /// Returns the board mirrored at the middle column
+    pub fn mirror(&self) -> Self {
+        let mirror_bits = |bits: B| {
+            (0..WIDTH).fold(B::ZERO, |mirrored, column| {
+                let column_bits = (bits >> (column * Self::COLUMN_BITS)) & Self::first_column();
+                mirrored | column_bits << ((WIDTH - 1 - column) * Self::COLUMN_BITS)
+            })
+        };
+        Board {
+            player: mirror_bits(self.player),
+            occupied: mirror_bits(self.occupied),
+        }
+    }
+
     /// Returns whether the current player has won
     pub fn has_won(&self) -> bool {
-        BitBoard::is_winning_board(self.player)
+        if MISERE {
+            Self::is_winning_board(self.occupied - self.player)
+        } else {
+            Self::is_winning_board(self.player)
+        }
     }
 
     /// Returns whether the current player has lost
     pub fn has_lost(&self) -> bool {
-        BitBoard::is_winning_board(self.occupied - self.player)
+        if MISERE {
+            Self::is_winning_board(self.player)
+        } else {
+            Self::is_winning_board(self.occupied - self.player)
+        }
     }
 
     pub fn is_move_valid(&self, column: u8) -> bool {
         let all_valid = self.all_possible_moves();
-        BitBoard::move_in_row(all_valid, column as u64) > 0
+        Self::move_in_row(all_valid, column as u64) != B::ZERO
     }
 
-    pub fn play_column(&self, column: u8, player: FieldType) -> Option<BitBoard> {
+    /// Places a stone of the given player into the column, starting at 0 on the left.
+    /// The player to move does not change
+    pub fn play_column(&self, column: u8, player: FieldType) -> Result<Self, BoardError> {
+        if column as usize >= WIDTH {
+            return Err(BoardError::ColumnOutOfRange { column });
+        }
         let all_valid = self.all_possible_moves();
-        let mov = BitBoard::move_in_row(all_valid, column as u64);
+        let mov = Self::move_in_row(all_valid, column as u64);
 
-        if mov > 0 {
+        if mov != B::ZERO {
             let board = match player {
                 FieldType::Opponent => {
-                    BitBoard {
+                    Board {
                         player: self.player,
                         occupied: self.occupied | mov,
                     }
                 }
                 FieldType::Player => {
-                    BitBoard {
+                    Board {
                         player: self.player | mov,
                         occupied: self.occupied | mov,
                     }
                 }
             };
-            return Some(board);
+            return Ok(board);
         }
-        return None;
+        Err(BoardError::ColumnFull { column })
     }
 }
 
 /// Stores the stones of one player in "player".
 /// All occupied fields are marked in "occupied".
 /// -> player2 = !player & occupied
-impl BitBoard {
-    pub const FIRST_COLUMN: u64 = 1 + (1 << 1) + (1 << 2) + (1 << 3) + (1 << 4) + (1 << 5) + (1 << 6) + (1 << 7);
-    pub const BOTTOM_LINE: u64 = 1 + (1 << 8) + (1 << 16) + (1 << 24) + (1 << 32) + (1 << 40) + (1 << 48) + (1 << 56);
-    pub const TOP_LINE: u64 = (1 << 7) + (1 << 15) + (1 << 23) + (1 << 31) + (1 << 39) + (1 << 47) + (1 << 55) + (1 << 63);
-    /// Exclude The top two rows (the 2 extra bits per column that store no information)
-    /// and the last (unnecessary) column with no information
-    pub const PLAYABLE_FIELDS: u64 = u64::MAX
-        & !(BitBoard::TOP_LINE | (BitBoard::TOP_LINE >> 1))
-        & !(BitBoard::FIRST_COLUMN << 56);
+impl<const WIDTH: usize, const HEIGHT: usize, B: Bits, const N: usize, const CYLINDRICAL: bool, const MISERE: bool>
+    Board<WIDTH, HEIGHT, B, N, CYLINDRICAL, MISERE> {
+    /// Bits per column: One for every row and an empty one above,
+    /// so that shifted rows of four never wrap around into the next column
+    pub const COLUMN_BITS: usize = {
+        assert!(WIDTH * (HEIGHT + 1) <= B::BITS, "The board does not fit into its bits");
+        assert!(N >= 2, "At least two stones have to be in a row");
+        HEIGHT + 1
+    };
+    /// Shift to the neighbouring field in the next column
+    const HORIZONTAL: usize = Self::COLUMN_BITS;
+    /// Shift to the field one column to the right and one row up
+    const UP_RIGHT: usize = Self::COLUMN_BITS + 1;
+    /// Shift to the field one column to the left and one row up
+    const UP_LEFT: usize = Self::COLUMN_BITS - 1;
+    /// Bits of all columns, the lines of cylindrical boards wrap around after them
+    const BOARD_BITS: usize = WIDTH * Self::COLUMN_BITS;
+    /// A horizontal line on a cylinder would reuse its fields, if it is longer than a row
+    const HORIZONTAL_LINES: bool = !CYLINDRICAL || N <= WIDTH;
+
+    // The masks are computed as u128 for every storage and truncated on use.
+    // See impl_masks for the public constants
+    const FIRST_COLUMN_MASK: u128 = (1 << Self::COLUMN_BITS) - 1;
+    const BOTTOM_LINE_MASK: u128 = bottom_line(WIDTH, Self::COLUMN_BITS);
+    const TOP_LINE_MASK: u128 = Self::BOTTOM_LINE_MASK << HEIGHT;
+    const PLAYABLE_MASK: u128 = Self::BOTTOM_LINE_MASK * ((1 << HEIGHT) - 1);
+    const ODD_ROWS_MASK: u128 = Self::BOTTOM_LINE_MASK * (0x5555_5555_5555_5555 & ((1 << HEIGHT) - 1));
+
+    #[inline(always)]
+    fn first_column() -> B {
+        B::from_mask(Self::FIRST_COLUMN_MASK)
+    }
+
+    #[inline(always)]
+    fn bottom_line() -> B {
+        B::from_mask(Self::BOTTOM_LINE_MASK)
+    }
+
+    #[inline(always)]
+    fn top_line() -> B {
+        B::from_mask(Self::TOP_LINE_MASK)
+    }
+
+    #[inline(always)]
+    fn playable_fields() -> B {
+        B::from_mask(Self::PLAYABLE_MASK)
+    }
+
+    /// All bits used by the board, including the empty one above every column
+    #[inline(always)]
+    fn board_fields() -> B {
+        B::from_mask(Self::BOTTOM_LINE_MASK * Self::FIRST_COLUMN_MASK)
+    }
+
+    #[inline(always)]
+    fn odd_rows() -> B {
+        B::from_mask(Self::ODD_ROWS_MASK)
+    }
+
+    #[inline(always)]
+    fn even_rows() -> B {
+        B::from_mask(Self::PLAYABLE_MASK & !Self::ODD_ROWS_MASK)
+    }
 
+    /// Returns the board, if it can occur in a game
+    fn check_legal(self) -> Result<Self, BoardError> {
+        match self.validate().first() {
+            None => Ok(self),
+            Some(violation) => Err(BoardError::IllegalPosition(*violation)),
+        }
+    }
 
     /// Sets the corresponding field and changes the player
-    pub fn play_field(&self, field: u64) -> BitBoard {
-        BitBoard {
+    pub fn play_field(&self, field: B) -> Self {
+        Board {
             player: (self.occupied - self.player),
             occupied: self.occupied | field,
         }
     }
 
+    /// Returns the fields in the bottom row with a stone of the current player.
+    /// In PopOut these stones can be removed instead of playing a new one
+    #[inline]
+    pub fn possible_pops(&self) -> B {
+        self.player & Self::bottom_line()
+    }
+
+    /// Removes the stone at the bottom field, lets the stones above it fall down
+    /// and changes the player (PopOut). The field has to be one of possible_pops()
+    pub fn pop_field(&self, field: B) -> Self {
+        let column = Self::first_column() << field.trailing_zeros() as usize;
+        // The popped stone is shifted into the unused bit of the previous column
+        let pop = |bits: B| (bits & !column) | (((bits & column) >> 1) & column);
+        let occupied = pop(self.occupied);
+        Board {
+            player: occupied - pop(self.player),
+            occupied,
+        }
+    }
+
     /// Constructs a board from a string representing the board.
-    /// p is the playing player, o the opponent
+    /// p is the playing player, c the opponent
     /// n is nothing - an empty field.
-    pub fn from_string(input: &str) -> Result<BitBoard, String> {
+    /// The position has to be legal, see BitBoard::validate()
+    pub fn from_string(input: &str) -> Result<Self, BoardError> {
+        Self::from_string_unchecked(input)?.check_legal()
+    }
+
+    /// Same as from_string, but accepts positions that can not occur in a game.
+    /// Useful to test single features of the board
+    pub fn from_string_unchecked(input: &str) -> Result<Self, BoardError> {
         // Remove all whitespace
         let input: String = input.split_whitespace().collect();
 
-        if input.chars().count() != BOARD_WIDTH * BOARD_HEIGHT {
-            return Err(String::from(format!("The board string has to be of length 42, but has length {}", input.chars().count())));
+        if input.chars().count() != WIDTH * HEIGHT {
+            return Err(BoardError::InvalidLength { expected: WIDTH * HEIGHT, length: input.chars().count() });
         }
-        let mut board = BitBoard::default();
+        let mut board = Self::empty();
 
         // The first element of the string is the position in the up-left corner,
-        // Represented by the 6th byte
-        let mut bit: u64 = 1 << BOARD_HEIGHT - 1;
-
-        // Counts how many chars have been added to the current column
-        let mut column_count = 0;
-
-        for c in input.chars() {
-            // println!("Iteration");
-            if column_count > BOARD_HEIGHT {
-                // Shift to the next position at the left
-                bit = bit >> (BOARD_WIDTH * 8 + 1);
-                column_count = 0;
-            }
+        // the rows follow from top to bottom
+        for (index, c) in input.chars().enumerate() {
+            let column = index % WIDTH;
+            let row = HEIGHT - 1 - index / WIDTH;
+            let bit = Self::coordinate_to_field(column as u8, row as u8);
 
             if c == 'c' {
-                board.occupied = board.occupied | bit;
+                board.occupied |= bit;
             } else if c == 'p' {
                 // println!("Player at {}", bit);
-                board.occupied = board.occupied | bit;
-                board.player = board.player | bit;
+                board.occupied |= bit;
+                board.player |= bit;
             } else if c == 'n' {
                 // Do nothing
             } else {
-                return Err(String::from(format!("Invalid character \'{}\' encountered", c)));
+                return Err(BoardError::InvalidCharacter { character: c });
             }
-            column_count += 1;
-            bit <<= 8;
         }
         Ok(board)
     }
 
+    /// Reads a bitmask of fields, like moves or threats, in the format of from_string.
+    /// Every p is part of the mask, the fields don't have to be reachable in a game
+    pub fn mask_from_string(input: &str) -> Result<B, BoardError> {
+        Ok(Self::from_string_unchecked(input)?.player)
+    }
+
+    /// Constructs a board by playing the moves from the empty board.
+    /// Each move is the column as digit, starting with 1 on the left (e.g. "4453").
+    pub fn from_moves(moves: &str) -> Result<Self, MoveSequenceError> {
+        let mut board = Self::empty();
+
+        for (index, c) in moves.chars().enumerate() {
+            let column = match c.to_digit(10) {
+                Some(column) if column >= 1 && column <= WIDTH as u32 => column - 1,
+                _ => return Err(MoveSequenceError::InvalidCharacter { index, character: c }),
+            };
+            if board.has_lost() || board.has_won() {
+                return Err(MoveSequenceError::GameOver { index });
+            }
+            let mov = Self::move_in_row(board.all_possible_moves(), column as u64);
+            if mov == B::ZERO {
+                return Err(MoveSequenceError::ColumnFull { index, column: column as u8 });
+            }
+            board = board.play_field(mov);
+        }
+        Ok(board)
+    }
 
-    /// Returns if this move would win the game by looking for adjacent rows of three
-    pub fn is_winning_move(&self, field: u64) -> bool {
-        let v_mask: u64 = 0b111;
-        let h_mask: u64 = 1 + (1 << 8) + (1 << 16) + (1 << 24);
-        let d1_mask: u64 = 1 + (1 << 9) + (1 << 18) + (1 << 27); // 9 bits between
-        let d2_mask: u64 = (1 << 3) + (1 << 10) + (1 << 17) + (1 << 24); // 7 bits between
-        let board = self.player + field;
+    /// Returns if this move would win the game for the current player.
+    /// Always false for misère games, where completing a line loses
+    pub fn is_winning_move(&self, field: B) -> bool {
+        !MISERE && Self::is_winning_board(self.player | field)
+    }
 
-        let position_bit = u64::trailing_zeros(field) + 1;
 
-        // Check vertical. Only down is possible
-        let mask = v_mask << position_bit - 4;
-        let mut winning = (board & mask) == mask;
+    #[inline]
+    fn is_winning_board(player_position: B) -> bool {
+        let board = player_position;
 
-        // Check horizontal
-        let mask = h_mask << (position_bit - 1); // Left field of mask is now aligned
-        for i in 0..4 {
-            let new_mask = mask >> (i * 8);
-            winning |= (board & new_mask) == new_mask;
+        let mut winning = Self::lines(board, 1) != B::ZERO;
+        if Self::HORIZONTAL_LINES {
+            winning |= Self::lines(board, Self::HORIZONTAL) != B::ZERO;
         }
+        winning |= Self::lines(board, Self::UP_RIGHT) != B::ZERO;
+        winning |= Self::lines(board, Self::UP_LEFT) != B::ZERO;
+
+        winning
+    }
+
+    /// Returns the last fields of all lines of N fields in the direction of offset
+    #[inline(always)]
+    fn lines(fields: B, offset: usize) -> B {
+        Self::chains(fields, N, offset)
+    }
 
-        // Check first diagonal (up-right)
-        let mask = d1_mask << (position_bit - 1);
-        for i in 0..4 {
-            let new_mask = mask >> (i * 9);
-            winning |= (board & new_mask) == new_mask;
+    /// Returns the last fields of all chains of length fields in the direction of offset
+    #[inline(always)]
+    fn chains(fields: B, length: usize, offset: usize) -> B {
+        // Doubles the length of the chains as long as possible...
+        let mut chains = fields;
+        let mut current = 1;
+        while 2 * current <= length {
+            chains &= Self::shift(chains, current * offset);
+            current *= 2;
         }
+        // ... and completes them with two overlapping chains
+        if current < length {
+            chains &= Self::shift(chains, (length - current) * offset);
+        }
+        chains
+    }
 
-        // Check second diagonal (up-left)
-        let mask = d2_mask << (position_bit - 4);
-        for i in 0..4 {
-            let new_mask = mask >> (i * 7);
-            winning |= (board & new_mask) == new_mask;
+    /// Moves all fields by amount bits towards the last column. On cylindrical boards the
+    /// fields leaving the last column enter the board again at the first column
+    #[inline(always)]
+    fn shift(fields: B, amount: usize) -> B {
+        if CYLINDRICAL {
+            Self::rotate(fields, amount % Self::BOARD_BITS)
+        } else {
+            fields << amount
         }
+    }
 
-        return winning;
+    /// Moves all fields by amount bits towards the first column, see shift()
+    #[inline(always)]
+    fn shift_back(fields: B, amount: usize) -> B {
+        if CYLINDRICAL {
+            Self::rotate(fields, Self::BOARD_BITS - amount % Self::BOARD_BITS)
+        } else {
+            fields >> amount
+        }
     }
 
+    /// Rotates the bits of the board
+    #[inline(always)]
+    fn rotate(fields: B, amount: usize) -> B {
+        let fields = fields & Self::board_fields();
+        if amount == 0 || amount == Self::BOARD_BITS {
+            return fields;
+        }
+        ((fields << amount) | (fields >> (Self::BOARD_BITS - amount))) & Self::board_fields()
+    }
 
-    #[inline]
-    fn is_winning_board(player_position: u64) -> bool {
-        let board = player_position;
 
-        // Check vertical
-        let v = (board << 2) & board;
-        let mut winning = ((v << 1) & v) > 0;
+    /// Checks whether the position can occur in a game.
+    /// Returns all violations of the rules, an empty list for legal positions.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
 
-        // Check horizontal
-        let h = (board << 16) & board;
-        winning |= ((h << 8) & h) > 0;
+        if self.occupied & !Self::playable_fields() != B::ZERO || self.player & !self.occupied != B::ZERO {
+            violations.push(Violation::OutsideBoard);
+        }
 
-        // Check diagonal up_right
-        let d1 = (board << 18) & board;
-        winning |= ((d1 << 9) & d1) > 0;
+        for column in 0..WIDTH as u8 {
+            for row in 1..HEIGHT as u8 {
+                let field = Self::coordinate_to_field(column, row);
+                if self.occupied & field != B::ZERO && self.occupied & (field >> 1) == B::ZERO {
+                    violations.push(Violation::FloatingStone { column, row });
+                }
+            }
+        }
 
-        // Check diagonal up_left
-        let d2 = (board << 14) & board;
-        winning |= ((d2 << 7) & d2) > 0;
+        // The first player moves first, so the player to move has at most as many stones
+        let player = (self.player & self.occupied).count_ones();
+        let opponent = self.opponent().count_ones();
+        if player > opponent || opponent > player + 1 {
+            violations.push(Violation::StoneCount { player, opponent });
+        }
 
-        winning
-    }
+        // Only the last move may complete lines, no matter whether they win or lose
+        let player_line = Self::is_winning_board(self.player);
+        let opponent_line = Self::is_winning_board(self.opponent());
+        if player_line && opponent_line {
+            violations.push(Violation::MultipleWinners);
+        } else if player_line {
+            // The opponent made a move after the player to move had completed a line
+            violations.push(Violation::WinNotOnLastMove);
+        } else if opponent_line {
+            // One of the highest stones of the opponent has to complete all of his fours
+            let opponent = self.opponent();
+            let highest = opponent & !(self.occupied >> 1);
+            let mut last_moves = highest;
+            let completes_all = std::iter::from_fn(|| {
+                let last_move = last_moves & last_moves.wrapping_neg();
+                last_moves ^= last_move;
+                (last_move != B::ZERO).then_some(last_move)
+            }).any(|last_move| !Self::is_winning_board(opponent ^ last_move));
+            if !completes_all {
+                violations.push(Violation::WinNotOnLastMove);
+            }
+        }
 
+        violations
+    }
 
     /// Returns a board representing all possible locations
     /// for a new move
     #[inline]
-    pub fn all_possible_moves(&self) -> u64 {
-        let x = BitBoard::PLAYABLE_FIELDS;
+    pub fn all_possible_moves(&self) -> B {
         // Sets the topmost line so that unplayed columns are taken into account
         // for the possible moves
-        let occupied = self.occupied | BitBoard::TOP_LINE;
+        let occupied = self.occupied | Self::top_line();
 
         // Shifts everything one field up and compares it to the state before.
         // The top positions of each row have been moved up.
         // Therefore all positions showing a difference are positions of the top fields
         // that have been moved.
-        let possible = occupied ^ ((occupied << 1) + 1);
-        possible & BitBoard::PLAYABLE_FIELDS
+        let possible = occupied ^ ((occupied << 1) + B::ONE);
+        possible & Self::playable_fields()
     }
 
     /// Returns the next possible move in the column out of all possible moves.
     /// Returns 0 if no move is possible
     #[inline]
-    pub fn move_in_row(possible_moves: u64, column: u64) -> u64 {
-        let column_mask = BitBoard::FIRST_COLUMN << (8 * column);
+    pub fn move_in_row(possible_moves: B, column: u64) -> B {
+        let column_mask = Self::first_column() << (Self::COLUMN_BITS * column as usize);
         column_mask & possible_moves
     }
 
     #[inline]
-    fn heuristic_helper_2(player_space: u64, player: u64, offset: u32) -> u32 {
-        let tmp = (player_space << 2 * offset) & player_space;
-        let possible_wins = (tmp << offset) & tmp;
-        let win_mask = possible_wins | (possible_wins << offset) | (possible_wins << 2 * offset) | (possible_wins << 3 * offset);
-        let score = (player & win_mask).count_ones();
-        score
+    fn heuristic_helper_2(player_space: B, player: B, offset: usize) -> u32 {
+        let possible_wins = Self::lines(player_space, offset);
+        let win_mask = (0..N).fold(B::ZERO, |mask, i| mask | Self::shift(possible_wins, i * offset));
+        (player & win_mask).count_ones()
     }
 
     pub fn heuristic_2(&self) -> i32 {
@@ -388,143 +777,433 @@ impl BitBoard {
         let occupied = self.occupied;
         let opponent = occupied - player;
 
-        BitBoard::material_score(player, opponent) - BitBoard::material_score(opponent, player)
+        Self::for_rules(Self::material_score(player, opponent) - Self::material_score(opponent, player))
     }
 
     pub fn heuristic3(&self) -> i32 {
-        let winning_player = BitBoard::winning_spots(self.player) & !self.occupied;
-        let winning_opponent = BitBoard::winning_spots(self.opponent()) & !self.occupied;
-        winning_player.count_ones() as i32 - winning_opponent.count_ones() as i32
+        let winning_player = Self::winning_spots(self.player) & !self.occupied;
+        let winning_opponent = Self::winning_spots(self.opponent()) & !self.occupied;
+        Self::for_rules(winning_player.count_ones() as i32 - winning_opponent.count_ones() as i32)
+    }
+
+    /// Inverts a heuristic score in misère games, where own lines are a liability
+    #[inline]
+    fn for_rules(score: i32) -> i32 {
+        if MISERE {
+            -score
+        } else {
+            score
+        }
     }
 
     #[inline]
-    fn material_score(player: u64, opponent: u64) -> i32 {
+    fn material_score(player: B, opponent: B) -> i32 {
         // All fields played or still playable by player
-        let player_space = (BitBoard::PLAYABLE_FIELDS & !opponent);
+        let player_space = Self::playable_fields() & !opponent;
 
         // Vertical
-        let mut score = BitBoard::heuristic_helper_2(player_space, player, 1);
+        let mut score = Self::heuristic_helper_2(player_space, player, 1);
         // Horizontal
-        score += BitBoard::heuristic_helper_2(player_space, player, 8);
+        if Self::HORIZONTAL_LINES {
+            score += Self::heuristic_helper_2(player_space, player, Self::HORIZONTAL);
+        }
         // Diagonal 1
-        score += BitBoard::heuristic_helper_2(player_space, player, 9);
+        score += Self::heuristic_helper_2(player_space, player, Self::UP_RIGHT);
         // Diagonal 2
-        score += BitBoard::heuristic_helper_2(player_space, player, 7);
+        score += Self::heuristic_helper_2(player_space, player, Self::UP_LEFT);
         score as i32
     }
 
-    /// Calculates the heuristic score of the board
+    /// Calculates the heuristic score of the board.
+    /// Counts the open chains of N - 1 and N - 2 stones of both players
     pub fn heuristic(&self) -> i32 {
         let player = self.player;
         let occupied = self.occupied;
         let opponent = occupied - player;
 
-        let score_p = BitBoard::num_chains(player, occupied);
-        let score_o = BitBoard::num_chains(opponent, occupied);
+        let score_p = Self::num_chains(player, occupied);
+        let score_o = Self::num_chains(opponent, occupied);
 
-        (score_p.three - score_o.three) * 2 + (score_p.two - score_o.two)
+        Self::for_rules((score_p.long - score_o.long) * 2 + (score_p.short - score_o.short))
     }
 
-    /// Returns the number of three-chains and the board without them
+    /// Returns the number of open chains of length stones and the stones of all chains.
+    /// A chain is closed if the fields before and after it are occupied or outside the board
     #[inline]
-    fn chain_helper(player: u64, occupied_closed: u64, closed_mask: u64, offset: u64) -> (i32, u64) {
-        let chains_three = (player << offset) & (player >> offset) & player;
-        let closed_r = (chains_three << 2 * offset) & occupied_closed;
-        let closed_l = (chains_three >> 2 * offset) & occupied_closed;
-        let closed = (closed_l << 4 * offset) & closed_r;
-        let closed_border = (closed_mask & chains_three) & (closed_r >> 2 * offset);
-        let chains = chains_three.count_ones() as i32 - closed.count_ones() as i32 - closed_border.count_ones() as i32;
-
-        let without_three = player - (chains_three | chains_three << offset | chains_three >> offset);
-        (chains, without_three)
-    }
-    /// Returns the chains of two
+    fn chain_helper(player: B, empty: B, length: usize, offset: usize) -> (i32, B) {
+        // The last stone of every chain
+        let chains = Self::chains(player, length, offset);
+        let open_before = Self::shift(empty, length * offset);
+        let open_after = Self::shift_back(empty, offset);
+        let open = chains & (open_before | open_after);
+
+        let stones = (0..length).fold(B::ZERO, |stones, i| stones | Self::shift_back(chains, i * offset));
+        (open.count_ones() as i32, stones)
+    }
+
+    /// Counts the open chains of N - 1 and N - 2 stones in one direction.
+    /// Stones of the longer chains are not counted again for the shorter ones
     #[inline]
-    fn chain_helper_two(player: u64, occupied_closed: u64, closed_mask: u64, offset: u64) -> i32 {
-        let chains_two = (player << offset) & player;
-        let closed_r = (chains_two << offset) & occupied_closed;
-        let closed_l = (chains_two >> 2 * offset) & occupied_closed;
-        let closed = (closed_l << 3 * offset) & closed_r;
-        let closed_border = (closed_mask & chains_two) & (closed_r >> 2 * offset);
-
-        let chains = chains_two.count_ones() as i32 - closed.count_ones() as i32 - closed_border.count_ones() as i32;
-        chains
+    fn chains_in_direction(player: B, empty: B, offset: usize) -> OpenChains {
+        let (long, stones) = Self::chain_helper(player, empty, N - 1, offset);
+        let short = if N > 2 {
+            Self::chain_helper(player & !stones, empty, N - 2, offset).0
+        } else {
+            0
+        };
+        OpenChains { long, short }
     }
 
-    /// Counts the number of (open) three-chains and two-chains in the board
-    /// Returns: (chains_three, chains_two)
-    fn num_chains(player: u64, occupied: u64) -> OpenChains {
-        let occupied_closed = occupied | !BitBoard::PLAYABLE_FIELDS;
+    /// Counts the number of open chains of N - 1 and N - 2 stones in the board
+    fn num_chains(player: B, occupied: B) -> OpenChains {
+        let empty = Self::playable_fields() & !occupied;
 
         // Vertical
-        let closed_mask: u64 = 0x2;
-        let (number, without) = BitBoard::chain_helper(player, occupied_closed, closed_mask, 1);
-        let mut chains_two = BitBoard::chain_helper_two(without, occupied_closed, closed_mask, 1);
-        let mut chains_three = number;
-
+        let mut chains = Self::chains_in_direction(player, empty, 1);
         // Horizontal
-        let closed_mask: u64 = 0x3F00;
-        let (number, without) = BitBoard::chain_helper(player, occupied_closed, closed_mask, 8);
-        chains_two += BitBoard::chain_helper_two(without, occupied_closed, closed_mask, 8);
-        chains_three += number;
-
+        if Self::HORIZONTAL_LINES {
+            chains += Self::chains_in_direction(player, empty, Self::HORIZONTAL);
+        }
         // Diagonal up-right
-        let closed_mask: u64 = 0x21E00;
-        let (number, without) = BitBoard::chain_helper(player, occupied_closed, closed_mask, 9);
-        chains_two += BitBoard::chain_helper_two(without, occupied_closed, closed_mask, 9);
-        chains_three += number;
-
+        chains += Self::chains_in_direction(player, empty, Self::UP_RIGHT);
         // Diagonal down-right
-        let closed_mask: u64 = 0x1E00;
-        let (number, without) = BitBoard::chain_helper(player, occupied_closed, closed_mask, 7);
-        chains_two += BitBoard::chain_helper_two(without, occupied_closed, closed_mask, 7);
-        chains_three += number;
-
-        OpenChains { three: chains_three, two: chains_two }
+        chains += Self::chains_in_direction(player, empty, Self::UP_LEFT);
+        chains
     }
 
+    /// Returns the fields that complete a line of N fields in the direction of offset
     #[inline]
-    fn winning_spots_helper(player: u64, offset: u64) -> u64 {
-        let mut p = 0;
-        let two_h = (player << offset) & player;
-        p |= (two_h << offset) & (player >> offset);
-        p |= (two_h >> 2 * offset) & (player << offset);
-        let three_h = two_h & (two_h << offset);
-        p |= (three_h >> 3 * offset) | (three_h << offset);
+    fn winning_spots_helper(player: B, offset: usize) -> B {
+        // after[i]: The i fields following in the direction of offset are played
+        let mut after = [!B::ZERO; N];
+        for i in 1..N {
+            after[i] = after[i - 1] & Self::shift_back(player, i * offset);
+        }
+
+        // A winning spot has i played fields before and N - 1 - i after it
+        let mut before = !B::ZERO;
+        let mut p = B::ZERO;
+        for i in 0..N {
+            if i > 0 {
+                before &= Self::shift(player, i * offset);
+            }
+            p |= before & after[N - 1 - i];
+        }
         p
     }
 
     #[inline]
-    fn opponent(&self) -> u64 {
+    fn opponent(&self) -> B {
         self.occupied - self.player
     }
 
-    pub fn winning_spots(player: u64) -> u64 {
-        // Vertical
-        let mut p = (player << 1) & (player << 2) & (player << 3);
+    pub fn winning_spots(player: B) -> B {
+        // Vertical, the fields above are always empty
+        let mut p = (1..N).fold(!B::ZERO, |p, i| p & (player << i));
         // Horizontal
-        p |= BitBoard::winning_spots_helper(player, 8);
+        if Self::HORIZONTAL_LINES {
+            p |= Self::winning_spots_helper(player, Self::HORIZONTAL);
+        }
         // Diagonal (up_left)
-        p |= BitBoard::winning_spots_helper(player, 7);
+        p |= Self::winning_spots_helper(player, Self::UP_LEFT);
         // Diagonal (up_right)
-        p |= BitBoard::winning_spots_helper(player, 9);
-        p & BitBoard::PLAYABLE_FIELDS
+        p |= Self::winning_spots_helper(player, Self::UP_RIGHT);
+        p & Self::playable_fields()
+    }
+
+    /// Returns the moves that block an immediate win of the opponent.
+    /// There is nothing to block in misère games
+    pub fn forced_moves(&self) -> B {
+        if MISERE {
+            return B::ZERO;
+        }
+        Self::winning_spots(self.opponent()) & self.all_possible_moves()
+    }
+
+    /// Returns all possible moves that win the game immediately for the current player.
+    /// No move wins immediately in misère games
+    #[inline]
+    pub fn winning_moves(&self) -> B {
+        if MISERE {
+            return B::ZERO;
+        }
+        Self::winning_spots(self.player) & self.all_possible_moves()
+    }
+
+    /// Returns all possible moves that do not allow the opponent to win with his next move.
+    /// - If the opponent can win at two or more fields, every move loses and 0 is returned
+    /// - If the opponent can win at exactly one field, this field has to be played
+    /// - Fields directly beneath a winning spot of the opponent are never returned
+    ///
+    /// Does not check whether the current player can win immediately (see winning_moves()).
+    /// In misère games these are all moves that don't complete a line of the current player
+    pub fn possible_non_losing_moves(&self) -> B {
+        if MISERE {
+            return self.all_possible_moves() & !Self::winning_spots(self.player);
+        }
+        let opponent_wins = Self::winning_spots(self.opponent()) & !self.occupied;
+        let mut possible = self.all_possible_moves();
+
+        let forced = possible & opponent_wins;
+        if forced != B::ZERO {
+            if forced & (forced - B::ONE) != B::ZERO {
+                // More than one threat can not be blocked
+                return B::ZERO;
+            }
+            possible = forced;
+        }
+        // Playing beneath a threat lets the opponent play onto it
+        possible & !(opponent_wins >> 1)
+    }
+
+    /// Returns all fields above the given fields in the same column
+    #[inline]
+    fn fields_above(fields: B) -> B {
+        let mut above = (fields << 1) & Self::playable_fields();
+        for _ in 1..HEIGHT {
+            above |= (above << 1) & Self::playable_fields();
+        }
+        above
+    }
+
+    /// Whether the current player made the first move of the game
+    #[inline]
+    pub fn is_first_player(&self) -> bool {
+        self.number_of_stones().is_multiple_of(2)
+    }
+
+    /// Classifies the empty winning spots of both players by the parity of their row.
+    /// Threats lying above a threat of the other player in the same column can never be
+    /// played, as the lower threat decides the column first.
+    pub fn threat_parity(&self) -> ThreatParity<B> {
+        let threats_player = Self::winning_spots(self.player) & !self.occupied;
+        let threats_opponent = Self::winning_spots(self.opponent()) & !self.occupied;
+
+        ThreatParity {
+            player: PlayerThreats::classify::<WIDTH, HEIGHT>(threats_player, Self::fields_above(threats_opponent)),
+            opponent: PlayerThreats::classify::<WIDTH, HEIGHT>(threats_opponent, Self::fields_above(threats_player)),
+            player_is_first: self.is_first_player(),
+        }
+    }
+
+    /// Predicts the outcome of a quiet endgame from the parity of the threats.
+    /// Returns 1 if the current player wins, -1 if he loses and 0 for a draw.
+    /// Returns None if the position is not quiet (a player can win immediately)
+    /// and for misère games, where the parity rules don't apply.
+    ///
+    /// If the second player follows up every move of the first player in the same column,
+    /// the first player gets all odd and the second player all even rows. Therefore:
+    /// - The first player wins with a reachable odd threat
+    /// - Otherwise the second player wins with a reachable even threat
+    /// - Otherwise the game ends in a draw
+    pub fn predict_endgame(&self) -> Option<i32> {
+        if MISERE || self.winning_moves() != B::ZERO || self.forced_moves() != B::ZERO {
+            return None;
+        }
+
+        let parity = self.threat_parity();
+        let (first, second) = if parity.player_is_first {
+            (parity.player, parity.opponent)
+        } else {
+            (parity.opponent, parity.player)
+        };
+
+        let first_wins = if first.odd != B::ZERO {
+            1
+        } else if second.even != B::ZERO {
+            -1
+        } else {
+            0
+        };
+
+        if parity.player_is_first {
+            Some(first_wins)
+        } else {
+            Some(-first_wins)
+        }
+    }
+
+    /// Uses the predicted outcome of the threat parity if it is decisive
+    /// and falls back to heuristic3() otherwise
+    pub fn heuristic_parity(&self) -> i32 {
+        match self.predict_endgame() {
+            Some(outcome) if outcome != 0 => outcome * 50,
+            _ => self.heuristic3(),
+        }
+    }
+}
+
+/// A board of any size and rules. Code that works for every variant, like the search, is
+/// generic over this trait instead of repeating all parameters of Board
+pub trait Variant: Copy {
+    type Bits: Bits;
+    const WIDTH: usize;
+    const HEIGHT: usize;
+    const COLUMN_BITS: usize;
+    const MISERE: bool;
+
+    fn has_won(&self) -> bool;
+    fn has_lost(&self) -> bool;
+    fn number_of_stones(&self) -> u32;
+    fn as_standard(&self) -> Option<BitBoard>;
+    fn key(&self) -> Self::Bits;
+    fn play_field(&self, field: Self::Bits) -> Self;
+    fn all_possible_moves(&self) -> Self::Bits;
+    fn move_in_row(possible_moves: Self::Bits, column: u64) -> Self::Bits;
+    fn winning_moves(&self) -> Self::Bits;
+    fn forced_moves(&self) -> Self::Bits;
+    fn possible_non_losing_moves(&self) -> Self::Bits;
+    fn heuristic3(&self) -> i32;
+}
+
+impl<const WIDTH: usize, const HEIGHT: usize, B: Bits, const N: usize, const CYLINDRICAL: bool, const MISERE: bool>
+    Variant for Board<WIDTH, HEIGHT, B, N, CYLINDRICAL, MISERE> {
+    type Bits = B;
+    const WIDTH: usize = WIDTH;
+    const HEIGHT: usize = HEIGHT;
+    const COLUMN_BITS: usize = Self::COLUMN_BITS;
+    const MISERE: bool = MISERE;
+
+    #[inline]
+    fn has_won(&self) -> bool {
+        Board::has_won(self)
+    }
+
+    #[inline]
+    fn has_lost(&self) -> bool {
+        Board::has_lost(self)
+    }
+
+    #[inline]
+    fn number_of_stones(&self) -> u32 {
+        Board::number_of_stones(self)
+    }
+
+    #[inline]
+    fn as_standard(&self) -> Option<BitBoard> {
+        Board::as_standard(self)
+    }
+
+    #[inline]
+    fn key(&self) -> B {
+        Board::key(self)
+    }
+
+    #[inline]
+    fn play_field(&self, field: B) -> Self {
+        Board::play_field(self, field)
     }
 
-    pub fn forced_moves(&self) -> u64 {
-        BitBoard::winning_spots(self.opponent()) & self.all_possible_moves()
+    #[inline]
+    fn all_possible_moves(&self) -> B {
+        Board::all_possible_moves(self)
+    }
+
+    #[inline]
+    fn move_in_row(possible_moves: B, column: u64) -> B {
+        Self::move_in_row(possible_moves, column)
+    }
+
+    #[inline]
+    fn winning_moves(&self) -> B {
+        Board::winning_moves(self)
+    }
+
+    #[inline]
+    fn forced_moves(&self) -> B {
+        Board::forced_moves(self)
+    }
+
+    #[inline]
+    fn possible_non_losing_moves(&self) -> B {
+        Board::possible_non_losing_moves(self)
+    }
+
+    #[inline]
+    fn heuristic3(&self) -> i32 {
+        Board::heuristic3(self)
     }
 }
 
+/// The masks of boards with the given storage
+macro_rules! impl_masks {
+    ($($bits:ty),*) => {
+        $(
+            impl<const WIDTH: usize, const HEIGHT: usize, const N: usize, const CYLINDRICAL: bool, const MISERE: bool>
+                Board<WIDTH, HEIGHT, $bits, N, CYLINDRICAL, MISERE> {
+                pub const FIRST_COLUMN: $bits = Self::FIRST_COLUMN_MASK as $bits;
+                pub const BOTTOM_LINE: $bits = Self::BOTTOM_LINE_MASK as $bits;
+                /// The empty bits above the columns
+                pub const TOP_LINE: $bits = Self::TOP_LINE_MASK as $bits;
+                pub const PLAYABLE_FIELDS: $bits = Self::PLAYABLE_MASK as $bits;
+                /// Fields in the rows 1, 3, 5, ... counted from 1 at the bottom
+                pub const ODD_ROWS: $bits = Self::ODD_ROWS_MASK as $bits;
+                /// Fields in the rows 2, 4, 6, ... counted from 1 at the bottom
+                pub const EVEN_ROWS: $bits = (Self::PLAYABLE_MASK & !Self::ODD_ROWS_MASK) as $bits;
+            }
+        )*
+    };
+}
+
+impl_masks!(u64, u128);
+
+/// Open chains of N - 1 (long) and N - 2 (short) stones
 struct OpenChains {
-    three: i32,
-    two: i32,
+    long: i32,
+    short: i32,
+}
+
+impl std::ops::AddAssign for OpenChains {
+    fn add_assign(&mut self, other: OpenChains) {
+        self.long += other.long;
+        self.short += other.short;
+    }
+}
+
+/// The threats of a single player (empty fields that would complete four in a row).
+/// Rows are counted from 1 at the bottom, so the bottom row is odd.
+#[derive(Default, Eq, PartialEq, Debug, Copy, Clone)]
+pub struct PlayerThreats<B = u64> {
+    /// Reachable threats in odd rows
+    pub odd: B,
+    /// Reachable threats in even rows
+    pub even: B,
+    /// Threats above a threat of the other player in the same column
+    pub unreachable: B,
+}
+
+impl PlayerThreats {
+    /// Fields in the rows 1, 3 and 5 of the standard board
+    pub const ODD_ROWS: u64 = BitBoard::ODD_ROWS;
+    /// Fields in the rows 2, 4 and 6 of the standard board
+    pub const EVEN_ROWS: u64 = BitBoard::EVEN_ROWS;
+}
+
+impl<B: Bits> PlayerThreats<B> {
+    fn classify<const WIDTH: usize, const HEIGHT: usize>(threats: B, blocked: B) -> Self {
+        let reachable = threats & !blocked;
+        PlayerThreats {
+            odd: reachable & Board::<WIDTH, HEIGHT, B>::odd_rows(),
+            even: reachable & Board::<WIDTH, HEIGHT, B>::even_rows(),
+            unreachable: threats & blocked,
+        }
+    }
+}
+
+/// Result of BitBoard::threat_parity()
+#[derive(Default, Eq, PartialEq, Debug, Copy, Clone)]
+pub struct ThreatParity<B = u64> {
+    pub player: PlayerThreats<B>,
+    pub opponent: PlayerThreats<B>,
+    /// Whether the current player made the first move. The first player profits
+    /// from odd threats, the second one from even threats.
+    pub player_is_first: bool,
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::board::BitBoard;
+    use crate::board::{BitBoard, Board, BoardError, FieldType, JsBoard, Move, MoveSequenceError, PlayerThreats, Violation};
 
     const EARLY_01: &str =
         "nnnnnnn
@@ -564,8 +1243,9 @@ mod tests {
             nnncpnn
             npnpcnn
             ncpppcn";
-        let player = BitBoard::from_string(EARLY_01).unwrap().player;
-        let player2 = BitBoard::from_string(board_01).unwrap().player;
+        // Both boards have more stones of the player to move than of the opponent
+        let player = BitBoard::from_string_unchecked(EARLY_01).unwrap().player;
+        let player2 = BitBoard::from_string_unchecked(board_01).unwrap().player;
         assert_eq!(BitBoard::winning_spots(player), 0);
         assert_eq!(BitBoard::winning_spots(player2).count_ones(), 4)
     }
@@ -586,15 +1266,17 @@ mod tests {
              pnncpnn
              npnnnpn
              nnpnnnp";
-        let board_1 = BitBoard::from_string(board_1).unwrap();
+        // Lines of one player only, partly floating
+        let board_1 = BitBoard::from_string_unchecked(board_1).unwrap();
         let player = board_1.player;
         let occupied = board_1.occupied;
-        assert_eq!(4, BitBoard::num_chains(player, occupied).three);
-        let board_2 = BitBoard::from_string(board_2).unwrap();
+        assert_eq!(4, BitBoard::num_chains(player, occupied).long);
+        // Also floating and without enough stones of the opponent
+        let board_2 = BitBoard::from_string_unchecked(board_2).unwrap();
         let player = board_2.player;
         let occupied = board_2.occupied;
-        assert_eq!(0, BitBoard::num_chains(player, occupied).three);
-        assert_eq!(0, BitBoard::num_chains(player, occupied).two);
+        assert_eq!(0, BitBoard::num_chains(player, occupied).long);
+        assert_eq!(0, BitBoard::num_chains(player, occupied).short);
     }
 
     #[test]
@@ -613,14 +1295,16 @@ mod tests {
             pnnpnnn
             pnnpnnn
             pnnpnnn";
-        let board_1 = BitBoard::from_string(board_1).unwrap();
+        // Lines of one player only
+        let board_1 = BitBoard::from_string_unchecked(board_1).unwrap();
         let player = board_1.player;
         let occupied = board_1.occupied;
-        assert_eq!(2, BitBoard::num_chains(player, occupied).three);
-        let board_2 = BitBoard::from_string(board_2).unwrap();
+        assert_eq!(2, BitBoard::num_chains(player, occupied).long);
+        // Also without enough stones of the opponent
+        let board_2 = BitBoard::from_string_unchecked(board_2).unwrap();
         let player = board_2.player;
         let occupied = board_2.occupied;
-        assert_eq!(0, BitBoard::num_chains(player, occupied).three);
+        assert_eq!(0, BitBoard::num_chains(player, occupied).long);
     }
 
     #[test]
@@ -639,14 +1323,16 @@ mod tests {
             pppcppp
             nnnnnnn
             pppcppp";
-        let board_1 = BitBoard::from_string(board_1).unwrap();
+        // Lines of one player only, partly floating
+        let board_1 = BitBoard::from_string_unchecked(board_1).unwrap();
         let player = board_1.player;
         let occupied = board_1.occupied;
-        assert_eq!(6, BitBoard::num_chains(player, occupied).three);
-        let board_2 = BitBoard::from_string(board_2).unwrap();
+        assert_eq!(6, BitBoard::num_chains(player, occupied).long);
+        // Also floating and without enough stones of the opponent
+        let board_2 = BitBoard::from_string_unchecked(board_2).unwrap();
         let player = board_2.player;
         let occupied = board_2.occupied;
-        assert_eq!(0, BitBoard::num_chains(player, occupied).three);
+        assert_eq!(0, BitBoard::num_chains(player, occupied).long);
     }
 
     #[test]
@@ -665,24 +1351,60 @@ mod tests {
              nnpcnnp
              npnnnpn
              pnnnpnn";
-        let board_1 = BitBoard::from_string(board_1).unwrap();
+        // Lines of one player only, partly floating
+        let board_1 = BitBoard::from_string_unchecked(board_1).unwrap();
         let player = board_1.player;
         let occupied = board_1.occupied;
-        assert_eq!(2, BitBoard::num_chains(player, occupied).three);
-        let board_2 = BitBoard::from_string(board_2).unwrap();
+        assert_eq!(2, BitBoard::num_chains(player, occupied).long);
+        // Also floating and without enough stones of the opponent
+        let board_2 = BitBoard::from_string_unchecked(board_2).unwrap();
         let player = board_2.player;
         let occupied = board_2.occupied;
-        assert_eq!(0, BitBoard::num_chains(player, occupied).three);
+        assert_eq!(0, BitBoard::num_chains(player, occupied).long);
     }
 
     #[test]
-    fn test_play_move() {
+    fn test_score_line_lengths() {
+        type ConnectThree = Board<7, 6, u64, 3>;
         let board =
             "nnnnnnn
-            nnnnnnn
-            nnnnnnn
-            nnnnnnn
-            nnnpcnn
+             nnnnnnn
+             nnnnnnn
+             nnnnnnn
+             pnnnnnn
+             ppnnnnn";
+        // Chains of two and single stones of one player only
+        let board = ConnectThree::from_string_unchecked(board).unwrap();
+        let chains = ConnectThree::num_chains(board.player, board.occupied);
+        assert_eq!(2, chains.long);
+        assert_eq!(5, chains.short);
+
+        // The chain of three only exists if the line wraps around
+        let board =
+            "nnnnnnn
+             nnnnnnn
+             nnnnnnn
+             nnnnnnn
+             nnnnnnn
+             pnnnnpp";
+        let cylinder = Board::<7, 6, u64, 4, true>::from_string_unchecked(board).unwrap();
+        assert_eq!(1, Board::<7, 6, u64, 4, true>::num_chains(cylinder.player, cylinder.occupied).long);
+        let flat = BitBoard::from_string_unchecked(board).unwrap();
+        assert_eq!(0, BitBoard::num_chains(flat.player, flat.occupied).long);
+
+        // A single row has no vertical or diagonal chains
+        let board = Board::<7, 1>::from_string_unchecked("pppnnnn").unwrap();
+        assert_eq!(2, board.heuristic());
+    }
+
+    #[test]
+    fn test_play_move() {
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnpcnn
             nnnpcnn";
         let board = BitBoard::from_string(board).unwrap();
 
@@ -693,7 +1415,7 @@ mod tests {
             nnnpnnn
             nnnnnnn
             nnnnnnn";
-        let to_play = BitBoard::from_string(to_play).unwrap().player;
+        let to_play = BitBoard::mask_from_string(to_play).unwrap();
 
         let expected =
             "nnnnnnn
@@ -729,9 +1451,10 @@ mod tests {
         assert_eq!(bits.player, 0);
         assert_eq!(bits.occupied, 0);
 
-        let bits = BitBoard::from_string(board_2).unwrap();
-        assert_eq!(bits.player, 1 << 9);
-        assert_eq!(bits.occupied, 1 << 9);
+        // A single floating stone
+        let bits = BitBoard::from_string_unchecked(board_2).unwrap();
+        assert_eq!(bits.player, 1 << 8);
+        assert_eq!(bits.occupied, 1 << 8);
     }
 
     #[test]
@@ -750,7 +1473,8 @@ mod tests {
             npnnnnn
             ncnnnnn
             ncnnnnn";
-        let bits_1 = BitBoard::from_string(board_1).unwrap();
+        // Stones of the player to move only
+        let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
         let bits_2 = BitBoard::from_string(board_2).unwrap();
 
         let player_move =
@@ -760,7 +1484,7 @@ mod tests {
             nnnnnnn
             nnnnnnn
             nnnnnnn";
-        let position = BitBoard::from_string(player_move).unwrap().player;
+        let position = BitBoard::mask_from_string(player_move).unwrap();
 
         assert!(bits_1.is_winning_move(position));
         assert!(!bits_2.is_winning_move(position));
@@ -782,7 +1506,8 @@ mod tests {
             npnnnnn
             ncnnnnn
             ncnnnnn";
-        let bits_1 = BitBoard::from_string(board_1).unwrap();
+        // The player to move has already won
+        let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
         let bits_2 = BitBoard::from_string(board_2).unwrap();
 
         assert!(bits_1.has_won());
@@ -812,9 +1537,10 @@ mod tests {
             nnnnnnn
             nnnnnnn
             nnnnppn";
-        let bits_1 = BitBoard::from_string(board_1).unwrap();
-        let bits_2 = BitBoard::from_string(board_2).unwrap();
-        let bits_3 = BitBoard::from_string(board_3).unwrap();
+        // Stones of the player to move only
+        let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
+        let bits_2 = BitBoard::from_string_unchecked(board_2).unwrap();
+        let bits_3 = BitBoard::from_string_unchecked(board_3).unwrap();
 
         let player_move =
             "nnnnnnn
@@ -823,7 +1549,7 @@ mod tests {
             nnnnnnn
             nnnnnnn
             nnnpnnn";
-        let position = BitBoard::from_string(player_move).unwrap().player;
+        let position = BitBoard::mask_from_string(player_move).unwrap();
 
         assert!(bits_1.is_winning_move(position));
         assert!(bits_2.is_winning_move(position));
@@ -853,9 +1579,10 @@ mod tests {
             nnnnnnn
             nnnnnnn
             nnnpppn";
-        let bits_1 = BitBoard::from_string(board_1).unwrap();
-        let bits_2 = BitBoard::from_string(board_2).unwrap();
-        let bits_3 = BitBoard::from_string(board_3).unwrap();
+        // Stones of the player to move only, the first two have already won
+        let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
+        let bits_2 = BitBoard::from_string_unchecked(board_2).unwrap();
+        let bits_3 = BitBoard::from_string_unchecked(board_3).unwrap();
 
 
         assert!(bits_1.has_won());
@@ -879,8 +1606,9 @@ mod tests {
             nnnpnnn
             nnpnnnn
             nnnnnnn";
-        let bits_1 = BitBoard::from_string(board_1).unwrap();
-        let bits_2 = BitBoard::from_string(board_2).unwrap();
+        // Stones of the player to move only, partly floating
+        let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
+        let bits_2 = BitBoard::from_string_unchecked(board_2).unwrap();
 
         let player_move =
             "nnnnnnn
@@ -889,7 +1617,7 @@ mod tests {
             nnnnnnn
             nnnnnnn
             nnnnnnn";
-        let position = BitBoard::from_string(player_move).unwrap().player;
+        let position = BitBoard::mask_from_string(player_move).unwrap();
 
         assert!(bits_1.is_winning_move(position));
         assert!(bits_2.is_winning_move(position));
@@ -911,8 +1639,9 @@ mod tests {
             nnnpnnn
             nnpnnnn
             nnnnnnn";
-        let bits_1 = BitBoard::from_string(board_1).unwrap();
-        let bits_2 = BitBoard::from_string(board_2).unwrap();
+        // Floating fours of the player to move
+        let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
+        let bits_2 = BitBoard::from_string_unchecked(board_2).unwrap();
 
 
         assert!(bits_1.has_won());
@@ -935,8 +1664,9 @@ mod tests {
             nnnpnnn
             nnnnpnn
             nnnnnnn";
-        let bits_1 = BitBoard::from_string(board_1).unwrap();
-        let bits_2 = BitBoard::from_string(board_2).unwrap();
+        // Stones of the player to move only, partly floating
+        let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
+        let bits_2 = BitBoard::from_string_unchecked(board_2).unwrap();
 
         let player_move =
             "nnnnnnn
@@ -945,7 +1675,7 @@ mod tests {
             nnnnnnn
             nnnnnnn
             nnnnnnn";
-        let position = BitBoard::from_string(player_move).unwrap().player;
+        let position = BitBoard::mask_from_string(player_move).unwrap();
 
         assert!(bits_1.is_winning_move(position));
         assert!(bits_2.is_winning_move(position));
@@ -967,8 +1697,9 @@ mod tests {
             nnnpnnn
             nnnnnnn
             nnnnnnn";
-        let bits_1 = BitBoard::from_string(board_1).unwrap();
-        let bits_2 = BitBoard::from_string(board_2).unwrap();
+        // Floating fours of the player to move
+        let bits_1 = BitBoard::from_string_unchecked(board_1).unwrap();
+        let bits_2 = BitBoard::from_string_unchecked(board_2).unwrap();
 
 
         assert!(bits_1.has_won());
@@ -993,19 +1724,990 @@ mod tests {
             pppnnnp";
 
         let board_1 = BitBoard::default();
-        let board_2 = BitBoard::from_string(board_2).unwrap();
+        // Stones of the player to move only
+        let board_2 = BitBoard::from_string_unchecked(board_2).unwrap();
 
         let possible = board_1.all_possible_moves();
         assert_eq!(possible, BitBoard::BOTTOM_LINE & BitBoard::PLAYABLE_FIELDS);
 
         let possible = board_2.all_possible_moves();
-        let expected = BitBoard::from_string(expected_2).unwrap().player;
+        let expected = BitBoard::mask_from_string(expected_2).unwrap();
         assert_eq!(possible, expected);
     }
+
+    #[test]
+    fn test_move_conversions() {
+        let mov = Move::from_coordinates(3, 2).unwrap();
+        assert_eq!(mov.get_move_repr(), 1 << 23);
+        assert!(mov.is_valid());
+        assert_eq!((mov.column(), mov.row()), (3, 2));
+
+        let field = mov.get_board_index();
+        assert_eq!(field.get_index(), 20);
+        assert_eq!(field.get_bit_pos(), 23);
+        assert_eq!(Move::from_field(&field), mov);
+
+        // All fields of the board convert back and forth
+        let board = BitBoard::empty();
+        for field in board.field_iter() {
+            let mov = Move::from_field(&field);
+            assert!(mov.is_valid());
+            assert_eq!(mov.get_board_index(), field);
+        }
+
+        assert_eq!(Move::from_coordinates(7, 0), Err(BoardError::ColumnOutOfRange { column: 7 }));
+        assert_eq!(Move::from_coordinates(0, 6), Err(BoardError::RowOutOfRange { row: 6 }));
+        assert!(Move::try_new(0).is_err());
+        assert!(Move::try_new(0b11).is_err());
+        assert!(Move::try_new(1 << 6).is_err());
+        assert!(Move::try_new(1 << 56).is_err());
+    }
+
+    #[test]
+    fn test_play_move_typed() {
+        let board = BitBoard::from_string(EARLY_02).unwrap();
+
+        let mov = Move::from_coordinates(3, 3).unwrap();
+        assert_eq!(board.play_move(mov), Ok(board.play_field(mov.get_move_repr())));
+        assert_eq!(board.is_winning(mov), Ok(false));
+
+        // Floating stones and occupied fields are rejected
+        let floating = Move::from_coordinates(0, 1).unwrap();
+        assert_eq!(board.play_move(floating), Err(BoardError::FieldNotPlayable { column: 0, row: 1 }));
+        assert!(board.play_move(Move::from_coordinates(3, 0).unwrap()).is_err());
+        assert_eq!(board.play_move(Move::new(0b11)), Err(BoardError::InvalidField { bits: 0b11 }));
+        assert!(board.is_winning(Move::new(0)).is_err());
+    }
+
+    #[test]
+    fn test_js_board_from_string() {
+        // Both players have three in a row
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            pppcccn";
+        let standard = JsBoard::from_string(board).unwrap();
+        assert_eq!((standard.line_length(), standard.is_misere()), (4, false));
+
+        let variant = JsBoard::from_variant_string(board, 5, true).unwrap();
+        assert_eq!((variant.line_length(), variant.is_misere()), (5, true));
+        assert_eq!(BitBoard::from(variant), BitBoard::from(standard));
+
+        assert!(matches!(JsBoard::from_variant_string(board, 3, false), Err(BoardError::IllegalPosition(_))));
+        assert_eq!(JsBoard::from_variant_string(board, 7, false), Err(BoardError::InvalidLineLength { length: 7 }));
+    }
+
+    #[test]
+    fn test_set_at_move() {
+        let mut board = BitBoard::empty();
+        board.set_at_move(Move::from_coordinates(2, 1).unwrap(), Some(FieldType::Opponent)).unwrap();
+        assert_eq!(board.get_at(2, 1), Some(FieldType::Opponent));
+        board.set_at_move(Move::from_coordinates(2, 1).unwrap(), None).unwrap();
+        assert_eq!(board, BitBoard::empty());
+
+        // Invalid bitmasks do not change the board
+        assert_eq!(board.set_at_move(Move::new(0b11), Some(FieldType::Player)), Err(BoardError::InvalidField { bits: 0b11 }));
+        assert_eq!(board.set_at_move(Move::new(1 << 6), Some(FieldType::Player)), Err(BoardError::InvalidField { bits: 1 << 6 }));
+        assert_eq!(board, BitBoard::empty());
+    }
+
+    #[test]
+    fn test_from_moves() {
+        assert_eq!(BitBoard::from_moves(""), Ok(BitBoard::empty()));
+
+        let board = BitBoard::from_moves("4454").unwrap();
+        let expected =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnncnnn
+            nnncnnn
+            nnnppnn";
+        assert_eq!(board, BitBoard::from_string(expected).unwrap());
+
+        assert_eq!(BitBoard::from_moves("448"), Err(MoveSequenceError::InvalidCharacter { index: 2, character: '8' }));
+        assert_eq!(BitBoard::from_moves("40"), Err(MoveSequenceError::InvalidCharacter { index: 1, character: '0' }));
+        assert_eq!(BitBoard::from_moves("1111111"), Err(MoveSequenceError::ColumnFull { index: 6, column: 0 }));
+        assert_eq!(BitBoard::from_moves("12121212"), Err(MoveSequenceError::GameOver { index: 7 }));
+        let error = BoardError::from(BitBoard::from_moves("448").unwrap_err());
+        assert_eq!(error, BoardError::InvalidMoveSequence(MoveSequenceError::InvalidCharacter { index: 2, character: '8' }));
+        assert_eq!(error.to_string(), "Invalid move sequence: Move 3: '8' is no column of the board");
+        assert_eq!(BitBoard::from_moves("1212121").map(|b| b.has_lost()), Ok(true));
+    }
+
+    #[test]
+    fn test_key() {
+        assert_eq!(BitBoard::empty().key(), (0..7).fold(0, |key, column| key | 1 << (column * 7)));
+
+        for moves in ["", "4", "4453", "1111112222223333334", "444444"] {
+            let board = BitBoard::from_moves(moves).unwrap();
+            assert!(board.key() < 1 << 49);
+            assert_eq!(BitBoard::from_key(board.key()), Ok(board));
+        }
+        let board = BitBoard::from_string(MID_02).unwrap();
+        assert_eq!(BitBoard::from_key(board.key()), Ok(board));
+        assert_ne!(board.key(), board.mirror().key());
+
+        // Every column needs a height marker
+        assert_eq!(BitBoard::from_key(0), Err(BoardError::InvalidKey { key: 0 }));
+        assert_eq!(BitBoard::from_key(1 << 49), Err(BoardError::InvalidKey { key: 1 << 49 }));
+    }
+
+    #[test]
+    fn test_to_string() {
+        for input in [EARLY_01, EARLY_02, MID_01, MID_02, ODD_THREAT, EVEN_THREAT, STACKED_THREATS] {
+            // EARLY_01 has too many stones of the player to move, MID_01 too many of the opponent
+            let board = BitBoard::from_string_unchecked(input).unwrap();
+            let rendered = board.to_string();
+
+            let expected: Vec<&str> = input.split_whitespace().collect();
+            assert_eq!(rendered, expected.join("\n"));
+            assert_eq!(BitBoard::from_string_unchecked(&rendered), Ok(board));
+        }
+    }
+
+    #[test]
+    fn test_to_pretty_string() {
+        let board = BitBoard::from_moves("4453").unwrap();
+        let expected = concat!(
+            "| . . . . . . . |\n",
+            "| . . . . . . . |\n",
+            "| . . . . . . . |\n",
+            "| . . . . . . . |\n",
+            "| . . . O . . . |\n",
+            "| . . O X X . . |\n",
+            "  1 2 3 4 5 6 7\n");
+        assert_eq!(board.to_pretty_string(false), expected);
+
+        let coloured = board.to_pretty_string(true);
+        assert!(coloured.contains("\x1b[31mX\x1b[0m"));
+        assert!(coloured.contains("\x1b[33mO\x1b[0m"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let legal_win =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnpnn
+            ccccppn";
+        let legal_win = BitBoard::from_string(legal_win).unwrap();
+        assert_eq!(legal_win.validate(), vec![]);
+        assert_eq!(BitBoard::from_key(legal_win.key()), Ok(legal_win));
+
+        let floating =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            pnnnnnn
+            nnncnnn";
+        // Illegal on purpose, the stone in the first column floats
+        let floating = BitBoard::from_string_unchecked(floating).unwrap();
+        assert_eq!(floating.validate(), vec![Violation::FloatingStone { column: 0, row: 1 }]);
+
+        let stone_count =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnpppn";
+        // Illegal on purpose, only the player to move has stones
+        let stone_count = BitBoard::from_string_unchecked(stone_count).unwrap();
+        assert_eq!(stone_count.validate(), vec![Violation::StoneCount { player: 3, opponent: 0 }]);
+        assert_eq!(BitBoard::from_key(stone_count.key()),
+                   Err(BoardError::IllegalPosition(Violation::StoneCount { player: 3, opponent: 0 })));
+
+        let multiple_winners =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            ccccnnn
+            ppppnnn";
+        // Illegal on purpose, both players have a four
+        let multiple_winners = BitBoard::from_string_unchecked(multiple_winners).unwrap();
+        assert_eq!(multiple_winners.validate(), vec![Violation::MultipleWinners]);
+
+        let player_won =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnncnn
+            ppppccc";
+        // Illegal on purpose, the player to move has already won
+        let player_won = BitBoard::from_string_unchecked(player_won).unwrap();
+        assert_eq!(player_won.validate(), vec![Violation::WinNotOnLastMove]);
+
+        // No single stone completes both fours of the opponent
+        let two_fours =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnc
+            nnnnppc
+            ppnnppc
+            ccccppc";
+        // Illegal on purpose, the opponent would have needed two last moves
+        let two_fours = BitBoard::from_string_unchecked(two_fours).unwrap();
+        assert_eq!(two_fours.validate(), vec![Violation::WinNotOnLastMove]);
+        assert!(BitBoard::from_string("nnnnnnn nnnnnnn nnnnnnc nnnnppc ppnnppc ccccppc").is_err());
+    }
+
+    #[test]
+    fn test_board_errors() {
+        assert_eq!(BitBoard::from_string("nnn"), Err(BoardError::InvalidLength { expected: 42, length: 3 }));
+        let invalid = "nnnnnnn nnnnnnn nnnnnnn nnnnnnn nnnnnnn nnnxnnn";
+        assert_eq!(BitBoard::from_string(invalid), Err(BoardError::InvalidCharacter { character: 'x' }));
+
+        let board = BitBoard::from_moves("444444").unwrap();
+        assert_eq!(board.play_column(2, FieldType::Opponent).map(|b| b.number_of_stones()), Ok(7));
+        assert_eq!(board.play_column(3, FieldType::Player), Err(BoardError::ColumnFull { column: 3 }));
+        assert_eq!(board.play_column(7, FieldType::Player), Err(BoardError::ColumnOutOfRange { column: 7 }));
+
+        let error: Box<dyn std::error::Error> = Box::new(BoardError::ColumnFull { column: 3 });
+        assert_eq!(error.to_string(), "Column 4 is full");
+        // Both error types count columns from 1
+        let error = BitBoard::from_moves("4444444").unwrap_err();
+        assert_eq!(error.to_string(), "Move 7: Column 4 is already full");
+        let error = BoardError::FieldNotPlayable { column: 0, row: 1 };
+        assert_eq!(error.to_string(), "The field in column 1 and row 2 can not be played");
+    }
+
+    #[test]
+    fn test_board_sizes() {
+        assert_eq!(Board::<5, 4>::PLAYABLE_FIELDS.count_ones(), 20);
+        assert_eq!(Board::<8, 7>::PLAYABLE_FIELDS.count_ones(), 56);
+        assert_eq!(Board::<8, 7>::TOP_LINE.leading_zeros(), 0);
+        assert_eq!(Board::<6, 5>::empty().all_possible_moves(), Board::<6, 5>::BOTTOM_LINE);
+
+        // Four in a row in the last column of the widest board
+        let board = Board::<8, 7>::from_moves("546677").unwrap();
+        assert!(board.is_winning_move(Board::<8, 7>::move_in_row(board.all_possible_moves(), 7)));
+        assert!(!board.is_winning_move(Board::<8, 7>::move_in_row(board.all_possible_moves(), 0)));
+        assert_eq!(Board::<8, 7>::from_moves("54667781"), Err(MoveSequenceError::GameOver { index: 7 }));
+        let won = Board::<8, 7>::from_moves("5466778").unwrap();
+        assert!(won.has_lost());
+        assert_eq!(Board::<8, 7>::from_key(won.key()), Ok(won));
+
+        // No four wraps around from the top of a column to the bottom of the next one
+        let wrapped =
+            "pnnnnn
+            pnnnnn
+            pnnnnn
+            cnnnnn
+            cpnnnn";
+        // The player to move has two stones more than the opponent
+        let board = Board::<6, 5>::from_string_unchecked(wrapped).unwrap();
+        assert!(!board.has_won());
+        assert_eq!(board.winning_moves(), 0);
+
+        let rendered =
+            "nnnnn
+            nnnnn
+            ncnnn
+            npcnn";
+        let board = Board::<5, 4>::from_string(rendered).unwrap();
+        assert_eq!(board.to_string(), "nnnnn\nnnnnn\nncnnn\nnpcnn");
+        assert_eq!(board.mirror().to_string(), "nnnnn\nnnnnn\nnnncn\nnncpn");
+        assert_eq!(board.to_pretty_string(false).lines().last(), Some("  1 2 3 4 5"));
+        assert_eq!(Board::<5, 4>::from_string(EARLY_01), Err(BoardError::InvalidLength { expected: 20, length: 42 }));
+    }
+
+    /// Compares winning_spots() to playing every empty field
+    fn check_winning_spots<const N: usize, const CYLINDRICAL: bool>(moves: &str) {
+        type Variant<const N: usize, const CYLINDRICAL: bool> = Board<7, 6, u64, N, CYLINDRICAL>;
+        let board = Variant::<N, CYLINDRICAL>::from_moves(moves).unwrap();
+        let spots = Variant::<N, CYLINDRICAL>::winning_spots(board.player);
+        for field in 0..64 {
+            let field = 1 << field;
+            if field & BitBoard::PLAYABLE_FIELDS & !board.occupied == 0 {
+                continue;
+            }
+            let wins = Variant::<N, CYLINDRICAL>::is_winning_board(board.player | field);
+            assert_eq!(spots & field != 0, wins, "{} with {} in a row at {:#x}", moves, N, field);
+        }
+    }
+
+    #[test]
+    fn test_line_lengths() {
+        // Three in a row in the bottom row
+        let board = Board::<7, 6, u64, 3>::from_moves("1122").unwrap();
+        assert_eq!(board.winning_moves(), Board::<7, 6, u64, 3>::move_in_row(board.all_possible_moves(), 2));
+        assert_eq!(BitBoard::from_moves("1122").unwrap().winning_moves(), 0);
+        assert_eq!(board.as_standard(), None);
+
+        // Four in a row only win Connect Four
+        assert_eq!(BitBoard::from_moves("11223344"), Err(MoveSequenceError::GameOver { index: 7 }));
+        let board = Board::<7, 6, u64, 5>::from_moves("11223344").unwrap();
+        assert!(!board.has_won() && !board.has_lost());
+        assert_eq!(board.winning_moves(), Board::<7, 6, u64, 5>::move_in_row(board.all_possible_moves(), 4));
+        assert_eq!(board.with_line_length::<4>().validate(), vec![Violation::MultipleWinners]);
+
+        // Six in a column fill it completely
+        let board = Board::<7, 6, u64, 6>::from_moves("1212121212").unwrap();
+        assert_eq!(board.winning_moves(), 1 << 5);
+        assert!(board.play_field(1 << 5).has_lost());
+
+        for moves in ["", "4", "4433", "123456712345671"] {
+            check_winning_spots::<3, false>(moves);
+        }
+        for moves in ["", "4433", "12345671234567123", "444444333333"] {
+            check_winning_spots::<4, false>(moves);
+            check_winning_spots::<5, false>(moves);
+        }
+        for moves in ["1122334455", "44444433333322", "1212121212"] {
+            check_winning_spots::<6, false>(moves);
+        }
+    }
+
+    #[test]
+    fn test_cylindrical() {
+        type Cylinder = Board<7, 6, u64, 4, true>;
+
+        // The bottom row wraps around from the last to the first column
+        let board = Cylinder::from_moves("647413").unwrap();
+        let moves = board.all_possible_moves();
+        assert_eq!(board.winning_moves(), Cylinder::move_in_row(moves, 1) | Cylinder::move_in_row(moves, 4));
+        assert_eq!(BitBoard::from_moves("647413").unwrap().winning_moves(), 0);
+        assert_eq!(board.as_standard(), None);
+        assert!(board.play_field(Cylinder::move_in_row(moves, 1)).has_lost());
+
+        let up_right =
+            "nnnnnnn
+            nnnnnnn
+            npnnnnn
+            pnnnnnn
+            nnnnnnp
+            nnnnnpn";
+        let up_left =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnpn
+            nnnnnnp
+            pnnnnnn
+            npnnnnn";
+        for board in [up_right, up_left] {
+            // Floating lines of one player only
+            assert!(Cylinder::from_string_unchecked(board).unwrap().has_won());
+            assert!(!BitBoard::from_string_unchecked(board).unwrap().has_won());
+        }
+
+        // A line longer than a row would reuse its fields
+        let row = "nnnnnnnnnnnnnnnnpppp";
+        // Stones of the player to move only
+        assert!(!Board::<4, 5, u64, 5, true>::from_string_unchecked(row).unwrap().has_won());
+        assert!(Board::<4, 5, u64, 4, true>::from_string_unchecked(row).unwrap().has_won());
+
+        for moves in ["", "4433", "12345671234567123", "444444333333", "7171", "67176"] {
+            check_winning_spots::<4, true>(moves);
+        }
+        for moves in ["", "4433", "123456712", "7171"] {
+            check_winning_spots::<3, true>(moves);
+            check_winning_spots::<5, true>(moves);
+        }
+    }
+
+    #[test]
+    fn test_misere() {
+        type Misere = Board<7, 6, u64, 4, false, true>;
+
+        // Completing the first column loses for the first player
+        let board = Misere::from_moves("121212").unwrap();
+        let moves = board.all_possible_moves();
+        assert_eq!(board.winning_moves(), 0);
+        assert_eq!(board.forced_moves(), 0);
+        assert_eq!(board.possible_non_losing_moves(), moves & !Misere::move_in_row(moves, 0));
+        assert!(!board.is_winning_move(Misere::move_in_row(moves, 0)));
+        assert_eq!(board.predict_endgame(), None);
+        assert_eq!(board.as_standard(), None);
+        assert_eq!(board.heuristic3(), -board.with_misere::<false>().heuristic3());
+
+        let completed = board.play_field(Misere::move_in_row(moves, 0));
+        assert!(completed.has_won() && !completed.has_lost());
+        assert!(!completed.with_misere::<false>().has_won() && completed.with_misere::<false>().has_lost());
+        assert_eq!(completed.validate(), vec![]);
+        assert_eq!(Misere::from_moves("12121212"), Err(MoveSequenceError::GameOver { index: 7 }));
+
+        // The second player has to avoid his own line as well
+        let board = Misere::from_moves("1212123").unwrap();
+        let moves = board.all_possible_moves();
+        assert_eq!(board.possible_non_losing_moves(), moves & !Misere::move_in_row(moves, 1));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        fn to_cbor<T: serde::Serialize>(value: &T) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            ciborium::into_writer(value, &mut bytes).unwrap();
+            bytes
+        }
+
+        let board = BitBoard::from_moves("443").unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(json, "\"nnnnnnn\\nnnnnnnn\\nnnnnnnn\\nnnnnnnn\\nnnnpnnn\\nnnccnnn\"");
+        assert_eq!(serde_json::from_str::<BitBoard>(&json).unwrap(), board);
+
+        // Binary formats store the key
+        let bytes = to_cbor(&board);
+        assert_eq!(ciborium::from_reader::<u64, _>(bytes.as_slice()).unwrap(), board.key());
+        assert_eq!(ciborium::from_reader::<BitBoard, _>(bytes.as_slice()).unwrap(), board);
+
+        let large = Board::<9, 7, u128>::from_moves("55667899").unwrap();
+        let bytes = to_cbor(&large);
+        let (lower, upper) = ciborium::from_reader::<(u64, u64), _>(bytes.as_slice()).unwrap();
+        assert_eq!((upper as u128) << 64 | lower as u128, large.key());
+        assert_eq!(ciborium::from_reader::<Board<9, 7, u128>, _>(bytes.as_slice()).unwrap(), large);
+        let json = serde_json::to_string(&large).unwrap();
+        assert_eq!(serde_json::from_str::<Board<9, 7, u128>>(&json).unwrap(), large);
+
+        // Only legal positions
+        let illegal = "\"nnnnnnn nnnnnnn nnnnnnn nnnnnnn nnnnnnn pppnnnn\"";
+        assert!(serde_json::from_str::<BitBoard>(illegal).is_err());
+        assert!(serde_json::from_str::<BitBoard>("\"nnn\"").is_err());
+        assert!(ciborium::from_reader::<BitBoard, _>(to_cbor(&(1u64 << 49)).as_slice()).is_err());
+
+        assert_eq!(serde_json::to_string(&FieldType::Player).unwrap(), "\"Player\"");
+        assert_eq!(serde_json::from_str::<FieldType>("\"Opponent\"").unwrap(), FieldType::Opponent);
+    }
+
+    #[test]
+    fn test_large_boards() {
+        type Board9x7 = Board<9, 7, u128>;
+        type Board10x8 = Board<10, 8, u128>;
+        assert_eq!(Board9x7::PLAYABLE_FIELDS.count_ones(), 63);
+        assert_eq!(Board10x8::PLAYABLE_FIELDS.count_ones(), 80);
+        assert_eq!(Board10x8::TOP_LINE.leading_zeros(), 128 - 90);
+        assert_eq!(Board10x8::empty().all_possible_moves(), Board10x8::BOTTOM_LINE);
+
+        // Four in a row in the last column, beyond the first 64 bits
+        let board = Board9x7::from_moves("657788").unwrap();
+        let last_column = Board9x7::move_in_row(board.all_possible_moves(), 8);
+        assert_eq!(last_column, 1 << 64);
+        assert_eq!(board.winning_moves(), last_column);
+
+        let board = Board10x8::from_moves("768899").unwrap();
+        let last_column = Board10x8::move_in_row(board.all_possible_moves(), 9);
+        assert_eq!(board.winning_moves(), last_column);
+        assert!(board.is_winning_move(last_column));
+        assert_eq!(board.forced_moves(), 0);
+        let won = board.play_field(last_column);
+        assert!(won.has_lost());
+        assert_eq!(Board10x8::from_key(won.key()), Ok(won));
+        assert_eq!(won.mirror().mirror(), won);
+
+        // A full column in the middle of the board
+        let board = Board10x8::from_moves("5555555566").unwrap();
+        assert!(!board.is_move_valid(4));
+        assert_eq!(board.play_column(4, FieldType::Player), Err(BoardError::ColumnFull { column: 4 }));
+        assert_eq!(board.all_possible_moves().count_ones(), 9);
+
+        let rendered =
+            "nnnnnnnnn
+            nnnnnnnnn
+            nnnnnnnnn
+            nnnnnnnnn
+            nnnnnnnnn
+            nnnnnnnnp
+            nnnnnnnnc";
+        let board = Board9x7::from_string(rendered).unwrap();
+        assert_eq!(board.get_at(8, 1), Some(FieldType::Player));
+        assert_eq!(Board9x7::from_string(&board.to_string()), Ok(board));
+    }
+
+    #[test]
+    fn test_mirror() {
+        let board = BitBoard::from_string(MID_02).unwrap();
+        let mirrored =
+           "nnnpnnn
+            nnpcnnn
+            nncpnnn
+            nnpcnnn
+            nncpnpn
+            ncpcncn";
+        let mirrored = BitBoard::from_string(mirrored).unwrap();
+
+        assert_eq!(board.mirror(), mirrored);
+        assert_eq!(board.mirror().mirror(), board);
+    }
+
+    #[test]
+    fn test_winning_moves() {
+        let board_1 =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            ppnpcnn";
+        let expected_1 =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnpnnnn";
+        // The player to move has two stones more than the opponent
+        let board_1 = BitBoard::from_string_unchecked(board_1).unwrap();
+        let expected_1 = BitBoard::mask_from_string(expected_1).unwrap();
+
+        assert_eq!(board_1.winning_moves(), expected_1);
+        assert_eq!(BitBoard::from_string(EARLY_02).unwrap().winning_moves(), 0);
+    }
+
+    #[test]
+    fn test_possible_non_losing_moves() {
+        // Single threat of the opponent has to be blocked
+        let board_1 =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            pnpnnnn
+            cccnnnn";
+        let expected_1 =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnpnnn";
+        // Two threats of the opponent can not be blocked
+        let board_2 =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnpnpnn
+            nncccnn";
+        // The opponent threatens to win in the second row of the fourth column
+        let board_3 =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            cccnnnn
+            ppcnnpp";
+        let expected_3 =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            pppnnnn
+            nnnnnpp
+            nnnnpnn";
+        let board_1 = BitBoard::from_string(board_1).unwrap();
+        let board_2 = BitBoard::from_string(board_2).unwrap();
+        let board_3 = BitBoard::from_string(board_3).unwrap();
+
+        assert_eq!(board_1.possible_non_losing_moves(), BitBoard::mask_from_string(expected_1).unwrap());
+        assert_eq!(board_2.possible_non_losing_moves(), 0);
+        assert_eq!(board_3.possible_non_losing_moves(), BitBoard::mask_from_string(expected_3).unwrap());
+    }
+
+    // The first player (to move) has an odd threat in the fourth column
+    const ODD_THREAT: &str =
+        "nnnnnnn
+        nnnnnnn
+        nnnnnnn
+        nnnnppp
+        nnnnpcc
+        nnnccpc";
+    // The second player has an even threat in the fourth column
+    const EVEN_THREAT: &str =
+        "nnnnnnn
+        nnnnnnn
+        nnnnnnn
+        nnnnnnn
+        nnnnccc
+        ppnnppc";
+    // The odd threat of the first player lies above the even threat of the second player
+    const STACKED_THREATS: &str =
+        "nnnnnnn
+        nnnnnnn
+        nnnnnnn
+        nnnnppp
+        nnnnccc
+        nnncppc";
+
+    #[test]
+    fn test_threat_parity() {
+        let threat_low =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnpnnn
+            nnnnnnn";
+        let threat_high =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnpnnn
+            nnnnnnn
+            nnnnnnn";
+        let threat_low = BitBoard::mask_from_string(threat_low).unwrap();
+        let threat_high = BitBoard::mask_from_string(threat_high).unwrap();
+
+        let parity = BitBoard::from_string(ODD_THREAT).unwrap().threat_parity();
+        assert!(parity.player_is_first);
+        assert_eq!(parity.player, PlayerThreats { odd: threat_high, even: 0, unreachable: 0 });
+        assert_eq!(parity.opponent, PlayerThreats::default());
+
+        let parity = BitBoard::from_string(EVEN_THREAT).unwrap().threat_parity();
+        assert_eq!(parity.player, PlayerThreats::default());
+        assert_eq!(parity.opponent, PlayerThreats { odd: 0, even: threat_low, unreachable: 0 });
+
+        let parity = BitBoard::from_string(STACKED_THREATS).unwrap().threat_parity();
+        assert_eq!(parity.player, PlayerThreats { odd: 0, even: 0, unreachable: threat_high });
+        assert_eq!(parity.opponent, PlayerThreats { odd: 0, even: threat_low, unreachable: 0 });
+    }
+
+    #[test]
+    fn test_predict_endgame() {
+        let odd_threat = BitBoard::from_string(ODD_THREAT).unwrap();
+        let even_threat = BitBoard::from_string(EVEN_THREAT).unwrap();
+
+        assert_eq!(odd_threat.predict_endgame(), Some(1));
+        assert_eq!(even_threat.predict_endgame(), Some(-1));
+        assert_eq!(BitBoard::from_string(EARLY_02).unwrap().predict_endgame(), Some(0));
+        // The threat of the second player has to be blocked immediately
+        assert_eq!(BitBoard::from_string(STACKED_THREATS).unwrap().predict_endgame(), None);
+    }
+}
+
+/// Writes the board in the format of BitBoard::from_string: One line per row, starting with
+/// the top row. p is the player to move, c the opponent and n an empty field.
+impl<const WIDTH: usize, const HEIGHT: usize, B: Bits, const N: usize, const CYLINDRICAL: bool, const MISERE: bool>
+    fmt::Display for Board<WIDTH, HEIGHT, B, N, CYLINDRICAL, MISERE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in (0..HEIGHT).rev() {
+            if row != HEIGHT - 1 {
+                writeln!(f)?;
+            }
+            for column in 0..WIDTH {
+                let c = match self.get_at(column as u8, row as u8) {
+                    Some(FieldType::Player) => 'p',
+                    Some(FieldType::Opponent) => 'c',
+                    None => 'n',
+                };
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Human readable formats like JSON store the board as string in the format of Display and
+/// BitBoard::from_string. Binary formats store the key of BitBoard::key(), as u64 for boards
+/// with up to 64 bits and as pair of the lower and upper 64 bits for larger ones, as many
+/// formats can't store a u128.
+/// Only legal positions can be deserialized, see BitBoard::validate()
+#[cfg(feature = "serde")]
+impl<const WIDTH: usize, const HEIGHT: usize, B: Bits, const N: usize, const CYLINDRICAL: bool, const MISERE: bool>
+    Serialize for Board<WIDTH, HEIGHT, B, N, CYLINDRICAL, MISERE> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else if B::BITS <= 64 {
+            serializer.serialize_u64(self.key().to_mask() as u64)
+        } else {
+            let key = self.key().to_mask();
+            (key as u64, (key >> 64) as u64).serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const WIDTH: usize, const HEIGHT: usize, B: Bits, const N: usize, const CYLINDRICAL: bool, const MISERE: bool>
+    Deserialize<'de> for Board<WIDTH, HEIGHT, B, N, CYLINDRICAL, MISERE> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let board = if deserializer.is_human_readable() {
+            let grid = String::deserialize(deserializer)?;
+            Self::from_string(&grid)
+        } else {
+            let key = if B::BITS <= 64 {
+                u64::deserialize(deserializer)? as u128
+            } else {
+                let (lower, upper) = <(u64, u64)>::deserialize(deserializer)?;
+                (upper as u128) << 64 | lower as u128
+            };
+            Self::from_key(B::from_mask(key))
+        };
+        board.map_err(D::Error::custom)
+    }
+}
+
+/// A reason why a position can not occur in a game
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Violation {
+    /// Stones outside of the board or inconsistent bits
+    OutsideBoard,
+    /// A stone without a stone below it (column and row start at 0 in the bottom left)
+    FloatingStone { column: u8, row: u8 },
+    /// The opponent has to have as many stones as the player to move or one more
+    StoneCount { player: u32, opponent: u32 },
+    /// Both players have a winning line
+    MultipleWinners,
+    /// The game was won before the last move
+    WinNotOnLastMove,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::OutsideBoard => write!(f, "Stones outside of the board"),
+            Violation::FloatingStone { column, row } => {
+                write!(f, "Floating stone in column {} and row {}", column + 1, row + 1)
+            }
+            Violation::StoneCount { player, opponent } => {
+                write!(f, "The player to move has {} stones, but the opponent has {}", player, opponent)
+            }
+            Violation::MultipleWinners => write!(f, "Both players have a winning line"),
+            Violation::WinNotOnLastMove => write!(f, "The game was won before the last move"),
+        }
+    }
+}
+
+/// Errors when reading a sequence of moves.
+/// The index is the position of the move in the sequence, starting at 0
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MoveSequenceError {
+    /// The character is no column of the board
+    InvalidCharacter { index: usize, character: char },
+    /// The column (starting at 0) is already full
+    ColumnFull { index: usize, column: u8 },
+    /// The game was already won before this move
+    GameOver { index: usize },
+}
+
+impl fmt::Display for MoveSequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveSequenceError::InvalidCharacter { index, character } => {
+                write!(f, "Move {}: '{}' is no column of the board", index + 1, character)
+            }
+            MoveSequenceError::ColumnFull { index, column } => {
+                write!(f, "Move {}: Column {} is already full", index + 1, column + 1)
+            }
+            MoveSequenceError::GameOver { index } => {
+                write!(f, "Move {}: The game is already over", index + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoveSequenceError {}
+
+/// Errors when constructing boards or playing moves.
+/// The messages count columns and rows from 1, like the move sequences
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BoardError {
+    /// The board string does not have one character per field
+    InvalidLength { expected: usize, length: usize },
+    /// The board string contains a character other than p, c and n
+    InvalidCharacter { character: char },
+    /// The column (starting at 0) is not on the board
+    ColumnOutOfRange { column: u8 },
+    /// The row (starting at 0) is not on the board
+    RowOutOfRange { row: u8 },
+    /// The column (starting at 0) is already full
+    ColumnFull { column: u8 },
+    /// The bitmask is not a single field of the board
+    InvalidField { bits: u64 },
+    /// The field is on the board, but can not be played right now
+    FieldNotPlayable { column: u8, row: u8 },
+    /// The value is no key of BitBoard::key()
+    InvalidKey { key: u128 },
+    /// The position can not occur in a game
+    IllegalPosition(Violation),
+    /// A player has already won or the board is full
+    GameOver,
+    /// Only 3 to 6 stones in a row are supported as winning line
+    InvalidLineLength { length: u8 },
+    /// The player to move has no stone at the bottom of the column to pop (PopOut)
+    NoStoneToPop { column: u8 },
+    /// A game record has fewer moves than the number of moves on the board
+    InvalidPly { ply: usize, moves: usize },
+    /// The sequence of moves can not be played
+    InvalidMoveSequence(MoveSequenceError),
+    /// The bytes are no valid endgame database
+    InvalidDatabase(DatabaseError),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::InvalidLength { expected, length } => {
+                write!(f, "The board string has to be of length {}, but has length {}", expected, length)
+            }
+            BoardError::InvalidCharacter { character } => write!(f, "Invalid character '{}' encountered", character),
+            BoardError::ColumnOutOfRange { column } => write!(f, "Column {} is outside of the board", *column as u32 + 1),
+            BoardError::RowOutOfRange { row } => write!(f, "Row {} is outside of the board", *row as u32 + 1),
+            BoardError::ColumnFull { column } => write!(f, "Column {} is full", column + 1),
+            BoardError::InvalidField { bits } => write!(f, "The bitmask {:#x} is no field of the board", bits),
+            BoardError::FieldNotPlayable { column, row } => {
+                write!(f, "The field in column {} and row {} can not be played", column + 1, row + 1)
+            }
+            BoardError::InvalidKey { key } => write!(f, "{:#x} is no valid position key", key),
+            BoardError::IllegalPosition(violation) => write!(f, "Invalid position: {}", violation),
+            BoardError::GameOver => write!(f, "The game is already over"),
+            BoardError::InvalidLineLength { length } => {
+                write!(f, "Connect {} is not supported, the line length has to be 3 to 6", length)
+            }
+            BoardError::NoStoneToPop { column } => write!(f, "Column {} has no own stone at the bottom", column + 1),
+            BoardError::InvalidPly { ply, moves } => {
+                write!(f, "{} moves can not be on the board, the game has only {} moves", ply, moves)
+            }
+            BoardError::InvalidMoveSequence(error) => write!(f, "Invalid move sequence: {}", error),
+            BoardError::InvalidDatabase(error) => write!(f, "Invalid endgame database: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+impl From<MoveSequenceError> for BoardError {
+    fn from(error: MoveSequenceError) -> BoardError {
+        BoardError::InvalidMoveSequence(error)
+    }
+}
+
+impl From<DatabaseError> for BoardError {
+    fn from(error: DatabaseError) -> BoardError {
+        BoardError::InvalidDatabase(error)
+    }
+}
+
+impl From<BoardError> for JsValue {
+    fn from(error: BoardError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+/// The standard board as seen from JavaScript, which can't handle generic types
+#[wasm_bindgen(js_name = BitBoard)]
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct JsBoard {
+    board: BitBoard,
+    /// Number of stones in a row that win, the N of Board
+    line_length: u8,
+    /// Whether completing a line loses, the MISERE of Board
+    misere: bool,
+}
+
+/// Runs the body with the board of the variant, typed with its line length and rules
+macro_rules! with_variant {
+    ($js_board:expr, $board:ident => $body:expr) => {
+        if $js_board.is_misere() {
+            $crate::board::with_variant!(@length $js_board, true, $board => $body)
+        } else {
+            $crate::board::with_variant!(@length $js_board, false, $board => $body)
+        }
+    };
+    (@length $js_board:expr, $misere:literal, $board:ident => $body:expr) => {
+        match $js_board.line_length() {
+            3 => { let $board = BitBoard::from($js_board).with_line_length::<3>().with_misere::<$misere>(); $body }
+            5 => { let $board = BitBoard::from($js_board).with_line_length::<5>().with_misere::<$misere>(); $body }
+            6 => { let $board = BitBoard::from($js_board).with_line_length::<6>().with_misere::<$misere>(); $body }
+            _ => { let $board = BitBoard::from($js_board).with_misere::<$misere>(); $body }
+        }
+    };
+}
+pub(crate) use with_variant;
+
+impl Default for JsBoard {
+    fn default() -> Self {
+        JsBoard::from(BitBoard::empty())
+    }
+}
+
+#[wasm_bindgen(js_class = BitBoard)]
+impl JsBoard {
+    pub fn empty() -> JsBoard {
+        JsBoard::default()
+    }
+
+    /// The empty board of the Connect N variant, where line_length stones in a row win
+    pub fn connect(line_length: u8) -> Result<JsBoard, BoardError> {
+        JsBoard::variant(line_length, false)
+    }
+
+    /// The empty board of the Connect N variant. With misère rules line_length stones
+    /// in a row lose instead
+    pub fn variant(line_length: u8, misere: bool) -> Result<JsBoard, BoardError> {
+        if !(3..=6).contains(&line_length) {
+            return Err(BoardError::InvalidLineLength { length: line_length });
+        }
+        Ok(JsBoard { board: BitBoard::empty(), line_length, misere })
+    }
+
+    pub fn line_length(&self) -> u8 {
+        self.line_length
+    }
+
+    pub fn is_misere(&self) -> bool {
+        self.misere
+    }
+
+    /// Reads a board of standard Connect Four, see Board::from_string.
+    /// Use from_variant_string for the other variants
+    pub fn from_string(input: &str) -> Result<JsBoard, BoardError> {
+        BitBoard::from_string(input).map(JsBoard::from)
+    }
+
+    /// Reads a board of the Connect N variant, which has to be legal under its rules
+    pub fn from_variant_string(input: &str, line_length: u8, misere: bool) -> Result<JsBoard, BoardError> {
+        let board = BitBoard::from_string_unchecked(input)?;
+        let js_board = JsBoard { board, ..JsBoard::variant(line_length, misere)? };
+        with_variant!(js_board, board => board.check_legal().map(|_| ()))?;
+        Ok(js_board)
+    }
+
+    pub fn play_column(&self, column: u8, player: FieldType) -> Result<JsBoard, BoardError> {
+        let board = self.board.play_column(column, player)?;
+        Ok(JsBoard { board, ..*self })
+    }
+
+    pub fn get_at(&self, x: u8, y: u8) -> Option<FieldType> {
+        self.board.get_at(x, y)
+    }
+
+    pub fn has_won(&self) -> bool {
+        with_variant!(*self, board => board.has_won())
+    }
+
+    pub fn has_lost(&self) -> bool {
+        with_variant!(*self, board => board.has_lost())
+    }
+
+    pub fn number_of_stones(&self) -> u32 {
+        self.board.number_of_stones()
+    }
+}
+
+impl From<BitBoard> for JsBoard {
+    fn from(board: BitBoard) -> JsBoard {
+        JsBoard { board, line_length: 4, misere: false }
+    }
+}
+
+/// Keeps the stones, but drops the line length and rules of the variant
+impl From<JsBoard> for BitBoard {
+    fn from(board: JsBoard) -> BitBoard {
+        board.board
+    }
 }
 
 #[wasm_bindgen]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FieldType {
     Opponent,
     Player,
@@ -0,0 +1,372 @@
+//! Database of exactly solved endgame positions
+//!
+//! The database is generated by retrograde analysis: All positions with at least K stones,
+//! that can be reached from a root position, are enumerated. Starting with the positions with
+//! the most stones, every position is solved by looking up the results of its successors.
+//! Mirrored positions share one entry.
+//!
+//! The root position should already have close to K stones. From the empty board, the
+//! number of positions grows far too fast: There are about 1.5 billion positions with 42
+//! stones alone.
+//!
+//! # File format
+//! All numbers are stored little endian.
+//!
+//! | Offset    | Size      | Content                                                  |
+//! |-----------|-----------|----------------------------------------------------------|
+//! | 0         | 4         | Magic bytes `C4DB`                                       |
+//! | 4         | 1         | Format version, currently 1                              |
+//! | 5         | 1         | Minimum number of stones K                               |
+//! | 6         | 2         | Reserved, always 0                                       |
+//! | 8         | 4         | Number of entries n                                      |
+//! | 12        | 8 * n     | Entries as u64, sorted ascending                         |
+//! | 12 + 8 n  | 4         | FNV-1a (32 bit) checksum of all previous bytes           |
+//!
+//! Each entry stores the position key in the upper 56 bits and the result as i8 in the lowest
+//! byte. The key of a position is the smaller `BitBoard::key()` of the position and its mirror
+//! image.
+//! The result counts the plies until the game is decided, including the winning move:
+//! Positive if the player to move wins, negative if he loses and 0 for a draw.
+
+use crate::board::{BitBoard, BOARD_HEIGHT, BOARD_WIDTH};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+const MAGIC: &[u8; 4] = b"C4DB";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 12;
+const CHECKSUM_SIZE: usize = 4;
+const BOARD_SIZE: u32 = (BOARD_WIDTH * BOARD_HEIGHT) as u32;
+
+#[wasm_bindgen]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct EndgameDatabase {
+    min_stones: u8,
+    /// Sorted entries consisting of the key and the result
+    entries: Vec<u64>,
+}
+
+#[wasm_bindgen]
+impl EndgameDatabase {
+    /// Reads a database in the documented file format
+    pub fn from_bytes(bytes: &[u8]) -> Result<EndgameDatabase, DatabaseError> {
+        if bytes.len() < HEADER_SIZE + CHECKSUM_SIZE {
+            return Err(DatabaseError::TooShort { length: bytes.len() });
+        }
+        let (data, checksum) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        if fnv1a(data) != read_u32(checksum) {
+            return Err(DatabaseError::ChecksumMismatch);
+        }
+        if &data[0..4] != MAGIC {
+            return Err(DatabaseError::NoDatabase);
+        }
+        if data[4] != VERSION {
+            return Err(DatabaseError::UnsupportedVersion { version: data[4] });
+        }
+
+        let min_stones = data[5];
+        let num_entries = read_u32(&data[8..12]) as usize;
+        let entry_bytes = &data[HEADER_SIZE..];
+        if num_entries.checked_mul(8) != Some(entry_bytes.len()) {
+            return Err(DatabaseError::InvalidLength { entries: num_entries, bytes: entry_bytes.len() });
+        }
+
+        let entries: Vec<u64> = entry_bytes.chunks_exact(8)
+            .map(|chunk| {
+                let mut entry = [0; 8];
+                entry.copy_from_slice(chunk);
+                u64::from_le_bytes(entry)
+            })
+            .collect();
+        if entries.windows(2).any(|pair| pair[0] >> 8 >= pair[1] >> 8) {
+            return Err(DatabaseError::UnsortedEntries);
+        }
+
+        Ok(EndgameDatabase { min_stones, entries })
+    }
+
+    /// Writes the database in the documented file format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.entries.len() * 8 + CHECKSUM_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&[VERSION, self.min_stones, 0, 0]);
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.to_le_bytes());
+        }
+        let checksum = fnv1a(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    /// Minimum number of stones of the stored positions
+    pub fn min_stones(&self) -> u8 {
+        self.min_stones
+    }
+
+    /// Number of stored positions
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl EndgameDatabase {
+    /// Solves all positions with at least min_stones stones reachable from root
+    pub fn generate(root: &BitBoard, min_stones: u8) -> EndgameDatabase {
+        // Positions ordered by their number of stones
+        let mut layers: Vec<Vec<BitBoard>> = vec![Vec::new(); BOARD_SIZE as usize + 1];
+        let mut visited = HashSet::new();
+        let mut stack = Vec::new();
+
+        if !is_terminal(root) {
+            visited.insert(canonical_key(root));
+            stack.push(*root);
+        }
+        while let Some(board) = stack.pop() {
+            let stones = board.number_of_stones();
+            if stones >= min_stones as u32 {
+                layers[stones as usize].push(board);
+            }
+            let mut possible_moves = board.all_possible_moves();
+            while possible_moves > 0 {
+                let mov = possible_moves & possible_moves.wrapping_neg();
+                possible_moves ^= mov;
+                let child = board.play_field(mov);
+                if !is_terminal(&child) && visited.insert(canonical_key(&child)) {
+                    stack.push(child);
+                }
+            }
+        }
+        drop(visited);
+
+        // Retrograde analysis: All successors are solved before their predecessors
+        let mut results: HashMap<u64, i8> = HashMap::new();
+        for layer in layers.iter().rev() {
+            for board in layer {
+                let result = solve_by_successors(board, |child| results[&canonical_key(child)]);
+                results.insert(canonical_key(board), result);
+            }
+        }
+
+        let mut entries: Vec<u64> = results.into_iter()
+            .map(|(key, result)| key << 8 | result as u8 as u64)
+            .collect();
+        entries.sort_unstable();
+
+        EndgameDatabase { min_stones, entries }
+    }
+
+    /// Returns the number of plies until the game is decided, including the winning move.
+    /// Positive if the player to move wins, negative if he loses and 0 for a draw.
+    /// Returns None if the position is not stored.
+    pub fn lookup(&self, board: &BitBoard) -> Option<i32> {
+        if board.number_of_stones() < self.min_stones as u32 {
+            return None;
+        }
+        let key = canonical_key(board);
+        self.entries.binary_search_by_key(&key, |entry| entry >> 8)
+            .ok()
+            .map(|index| self.entries[index] as u8 as i8 as i32)
+    }
+
+    /// Returns the result of the position together with the best move.
+    /// Returns None if the position or one of its successors is not stored.
+    pub fn best_move(&self, board: &BitBoard) -> Option<(i32, u64)> {
+        self.lookup(board)?;
+
+        let mut best: Option<(i32, u64)> = None;
+        let mut possible_moves = board.all_possible_moves();
+        while possible_moves > 0 {
+            let mov = possible_moves & possible_moves.wrapping_neg();
+            possible_moves ^= mov;
+            let child = board.play_field(mov);
+            let result = if child.has_lost() {
+                1
+            } else if child.number_of_stones() == BOARD_SIZE {
+                0
+            } else {
+                predecessor_result(self.lookup(&child)? as i8) as i32
+            };
+            if best.is_none_or(|(best, _)| rank(result as i8) > rank(best as i8)) {
+                best = Some((result, mov));
+            }
+        }
+        best
+    }
+}
+
+/// Reasons why bytes can not be read as an endgame database
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DatabaseError {
+    /// Not even the header and the checksum fit into the bytes
+    TooShort { length: usize },
+    /// The checksum does not match the content, the data is corrupted
+    ChecksumMismatch,
+    /// The magic bytes are missing
+    NoDatabase,
+    /// The format version is not supported
+    UnsupportedVersion { version: u8 },
+    /// The number of entries in the header does not match the data
+    InvalidLength { entries: usize, bytes: usize },
+    /// The entries are not sorted by key or contain a key twice
+    UnsortedEntries,
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::TooShort { length } => {
+                write!(f, "The database has to be at least {} bytes long, but has {} bytes", HEADER_SIZE + CHECKSUM_SIZE, length)
+            }
+            DatabaseError::ChecksumMismatch => write!(f, "The checksum of the database does not match"),
+            DatabaseError::NoDatabase => write!(f, "The data is no endgame database"),
+            DatabaseError::UnsupportedVersion { version } => write!(f, "Unsupported database version {}", version),
+            DatabaseError::InvalidLength { entries, bytes } => {
+                write!(f, "Expected {} entries, but found {} bytes", entries, bytes)
+            }
+            DatabaseError::UnsortedEntries => write!(f, "The entries of the database are not sorted"),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<DatabaseError> for JsValue {
+    fn from(error: DatabaseError) -> JsValue {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+/// Whether the game is over
+fn is_terminal(board: &BitBoard) -> bool {
+    board.has_lost() || board.number_of_stones() == BOARD_SIZE
+}
+
+/// Unique key of the position and its mirror image
+fn canonical_key(board: &BitBoard) -> u64 {
+    u64::min(board.key(), board.mirror().key())
+}
+
+/// Solves a non terminal position given the results of all non terminal successors
+fn solve_by_successors<F: Fn(&BitBoard) -> i8>(board: &BitBoard, successor: F) -> i8 {
+    let mut best = i8::MIN;
+    let mut possible_moves = board.all_possible_moves();
+    while possible_moves > 0 {
+        let mov = possible_moves & possible_moves.wrapping_neg();
+        possible_moves ^= mov;
+        let child = board.play_field(mov);
+        let result = if child.has_lost() {
+            // Winning move
+            1
+        } else if child.number_of_stones() == BOARD_SIZE {
+            0
+        } else {
+            predecessor_result(successor(&child))
+        };
+        if best == i8::MIN || rank(result) > rank(best) {
+            best = result;
+        }
+    }
+    best
+}
+
+/// Converts the result of a successor to the result of the player moving into it
+fn predecessor_result(result: i8) -> i8 {
+    match result {
+        0 => 0,
+        r if r > 0 => -(r + 1),
+        r => -r + 1,
+    }
+}
+
+/// Orders results from the view of the player to move: Fast wins, draws, slow losses
+fn rank(result: i8) -> i32 {
+    match result {
+        0 => 0,
+        r if r > 0 => 100 - r as i32,
+        r => -100 - r as i32,
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[0..4]);
+    u32::from_le_bytes(value)
+}
+
+/// 32 bit FNV-1a hash
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x01000193))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{BitBoard, BoardError};
+    use crate::endgame::{fnv1a, DatabaseError, EndgameDatabase};
+    use crate::engine::solve_weak;
+
+    const LATE_01: &str =
+           "ncnnnnc
+            nppnncc
+            nccnnpc
+            cppnccp
+            pcpppcp
+            ppccpcp";
+
+    #[test]
+    fn test_generate() {
+        let root = BitBoard::from_string(LATE_01).unwrap();
+        let database = EndgameDatabase::generate(&root, 30);
+
+        // The root and its mirror image share an entry
+        let result = database.lookup(&root).unwrap();
+        assert_eq!(database.lookup(&root.mirror()), Some(result));
+
+        // Same result as the search, which returns depth - plies + 1 for a win
+        let depth = 42 - root.number_of_stones() as u8;
+        let mut num_nodes = 0;
+        let (score, _) = solve_weak(root, depth, i32::MIN + 2, i32::MAX - 2, &mut num_nodes);
+        let plies = if score == 0 { 0 } else { score.signum() * (depth as i32 - score.abs() + 1) };
+        assert_eq!(result, plies);
+
+        let (best_result, best_move) = database.best_move(&root).unwrap();
+        assert_eq!(best_result, result);
+        assert!(best_move & root.all_possible_moves() > 0);
+
+        // Positions with less stones are not stored
+        assert_eq!(database.lookup(&BitBoard::empty()), None);
+    }
+
+    #[test]
+    fn test_file_format() {
+        let root = BitBoard::from_string(LATE_01).unwrap();
+        let database = EndgameDatabase::generate(&root, 32);
+
+        let mut bytes = database.to_bytes();
+        assert_eq!(&bytes[0..4], b"C4DB");
+        assert_eq!(bytes.len(), 12 + 8 * database.len() + 4);
+        assert_eq!(EndgameDatabase::from_bytes(&bytes), Ok(database));
+
+        // Any corruption is detected by the checksum
+        bytes[20] ^= 1;
+        assert_eq!(EndgameDatabase::from_bytes(&bytes), Err(DatabaseError::ChecksumMismatch));
+        assert_eq!(EndgameDatabase::from_bytes(&bytes[0..10]), Err(DatabaseError::TooShort { length: 10 }));
+        assert_eq!(BoardError::from(DatabaseError::ChecksumMismatch), BoardError::InvalidDatabase(DatabaseError::ChecksumMismatch));
+
+        // A valid checksum over other data
+        let mut bytes = b"C4DB\x02\x20\0\0\0\0\0\0".to_vec();
+        bytes.extend_from_slice(&fnv1a(&bytes).to_le_bytes());
+        assert_eq!(EndgameDatabase::from_bytes(&bytes), Err(DatabaseError::UnsupportedVersion { version: 2 }));
+
+        // More entries in the header than bytes in the data
+        let mut bytes = b"C4DB\x01\x20\0\0\xff\xff\xff\xff".to_vec();
+        bytes.extend_from_slice(&fnv1a(&bytes).to_le_bytes());
+        let entries = u32::MAX as usize;
+        assert_eq!(EndgameDatabase::from_bytes(&bytes), Err(DatabaseError::InvalidLength { entries, bytes: 0 }));
+    }
+}
@@ -1,141 +1,563 @@
-use crate::board::BitBoard;
+use crate::bits::Bits;
+use crate::board::{with_variant, BitBoard, BoardError, JsBoard, Move, Variant, BOARD_WIDTH};
+use crate::endgame::EndgameDatabase;
+use crate::game::Player;
+use crate::knowledge;
+use crate::popout::{self, position_key, PopOut, PopOutMove, REPETITIONS};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
 use wasm_bindgen::prelude::*;
-use std::cmp::max;
 
-const SEARCH_ORDER: [u64; 7] = [3,2,4,1,5,0,6];
+/// Remaining depth from which the knowledge based rules are tried before searching,
+/// if SearchOptions::knowledge_cutoffs is set. A proof costs about 50-120 µs and succeeds for
+/// only about 5% of random positions with 16 to 18 stones. In null window searches of such
+/// positions with depth 24 the cutoffs saved about 1% of the nodes, but took 2-20% more time
+/// (see bench_weak_knowledge). They are therefore only tried close to the root
+const KNOWLEDGE_MIN_DEPTH: u8 = 24;
+/// Number of moves searched with full depth before late move reductions start
+const FULL_DEPTH_MOVES: usize = 3;
+/// Minimum depth for late move reductions
+const REDUCTION_MIN_DEPTH: u8 = 3;
 
 #[wasm_bindgen]
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum SolverType {
     Strong, Weak
 }
 
 #[wasm_bindgen]
 #[derive(Default, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SolveResult {
     pub score: i32,
+    /// Bit of the chosen move. 0 for moves of boards stored in a u128 that don't fit into
+    /// a u64, use column and row there
     pub mov: u64,
+    /// Column of the chosen move, starting at 0 on the left. None if there is no move to play
+    pub column: Option<u8>,
+    /// Row the chosen move lands in, starting at 0 at the bottom
+    pub row: Option<u8>,
     pub nodes_searched: u64,
     // Win was found in x rounds
     pub end_in: i32,
 }
 
 
+/// Result of solve_popout()
 #[wasm_bindgen]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
-pub struct BestMove {
+pub struct PopOutResult {
     pub score: i32,
-    pub move_row: usize
+    /// None if there is no move to play
+    pub mov: Option<PopOutMove>,
+    pub nodes_searched: u64,
 }
 
-impl BestMove {
-    pub fn new(score: i32, move_row: usize) -> BestMove {
-        BestMove {
-            score, move_row
+/// Configures the heuristic search of solve_strong and the knowledge cutoffs of solve_weak
+#[wasm_bindgen]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SearchOptions {
+    /// Searches moves late in SEARCH_ORDER with reduced depth first.
+    /// Only if such a move beats the best move so far, it is searched again with full depth
+    pub late_move_reductions: bool,
+    /// Maximum number of forced plies searched beyond the depth
+    pub max_extensions: u8,
+    /// Tries to prove a draw for the second player with the knowledge based rules before
+    /// searching nodes with a remaining depth of at least KNOWLEDGE_MIN_DEPTH (weak search only).
+    /// Off by default, a proof rarely succeeds and every attempt costs about as much as a small
+    /// search
+    pub knowledge_cutoffs: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            late_move_reductions: false,
+            max_extensions: 4,
+            knowledge_cutoffs: false,
         }
     }
 }
 
+#[wasm_bindgen]
+impl SearchOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SearchOptions {
+        SearchOptions::default()
+    }
+}
+
 #[wasm_bindgen]
 impl SolveResult {
     pub fn new(score: i32, mov: u64) -> SolveResult {
+        SolveResult::on_board::<BitBoard>(score, mov)
+    }
+
+}
+
+impl SolveResult {
+    /// Same as new, but for a move on a board of any size
+    fn on_board<V: Variant>(score: i32, mov: V::Bits) -> SolveResult {
+        let field = mov.trailing_zeros() as usize;
+        let column_bits = V::COLUMN_BITS;
+        let valid = mov.count_ones() == 1 && field / column_bits < V::WIDTH && field % column_bits < V::HEIGHT;
         SolveResult {
-            score, mov,
+            score,
+            mov: u64::try_from(mov.to_mask()).unwrap_or(0),
+            column: valid.then(|| (field / column_bits) as u8),
+            row: valid.then(|| (field % column_bits) as u8),
             nodes_searched: 0,
             end_in: 0
         }
     }
 
+    /// The chosen move or None if there is no move to play.
+    /// Only meaningful for results on the standard board
+    pub fn best_move(&self) -> Option<Move> {
+        Move::try_new(self.mov).ok()
+    }
+}
+
+
+/// Searches the best move of the player to move. Fails if the game is already over
+pub fn solve<V: Variant>(start: &V, depth: u8, solver: SolverType) -> Result<SolveResult, BoardError> {
+    solve_with_options(start, depth, solver, &SearchOptions::default())
+}
+
+/// Same as solve, but uses the exact results of the endgame database where available
+pub fn solve_with_database(start: &BitBoard, depth: u8, solver: SolverType, database: &EndgameDatabase)
+    -> Result<SolveResult, BoardError> {
+    check_not_over(start)?;
+    Ok(Search::new(Some(database), SearchOptions::default()).solve(start, depth, solver))
+}
+
+/// Same as solve, but configures the heuristic search
+pub fn solve_with_options<V: Variant>(start: &V, depth: u8, solver: SolverType, options: &SearchOptions)
+    -> Result<SolveResult, BoardError> {
+    check_not_over(start)?;
+    Ok(Search::new(None, *options).solve(start, depth, solver))
+}
+
+#[wasm_bindgen(js_name = solve)]
+pub fn js_solve(start: &JsBoard, depth: u8, solver: SolverType) -> Result<SolveResult, BoardError> {
+    with_variant!(*start, board => solve(&board, depth, solver))
+}
+
+#[wasm_bindgen(js_name = solve_with_database)]
+pub fn js_solve_with_database(start: &JsBoard, depth: u8, solver: SolverType, database: &EndgameDatabase)
+    -> Result<SolveResult, BoardError> {
+    // The database only contains Connect Four positions
+    if start.line_length() == 4 && !start.is_misere() {
+        solve_with_database(&BitBoard::from(*start), depth, solver, database)
+    } else {
+        js_solve(start, depth, solver)
+    }
+}
+
+#[wasm_bindgen(js_name = solve_with_options)]
+pub fn js_solve_with_options(start: &JsBoard, depth: u8, solver: SolverType, options: &SearchOptions)
+    -> Result<SolveResult, BoardError> {
+    with_variant!(*start, board => solve_with_options(&board, depth, solver, options))
 }
 
+/// Searches the best move of PopOut for the player to move, with the scores of solve.
+/// A position that would occur for the third time is scored as draw.
+/// Fails if the game is already over
 #[wasm_bindgen]
-pub fn solve(start: &BitBoard, depth: u8, solver: SolverType) -> SolveResult {
-    let start = start.clone();
-    let depth = u8::min(depth, 42 - start.number_of_stones() as u8);
-    let mut nodes_searched: u64 = 0;
-
-    let (score, mov) = match solver {
-        SolverType::Strong => { solve_strong(start, depth, i32::MIN+2, i32::MAX-2, &mut nodes_searched)}
-        SolverType::Weak => { solve_weak(start, depth, i32::MIN+2, i32::MAX-2, &mut nodes_searched)}
-    };
-
-    let end_in = match solver {
-        SolverType::Strong => {0}
-        SolverType::Weak => {
-            if score == 0 {
-                0
-            } else {
-                depth as i32 - score.abs() + 1
-            }
-        }
-    };
-    SolveResult {
+pub fn solve_popout(game: &PopOut, depth: u8, solver: SolverType) -> Result<PopOutResult, BoardError> {
+    if game.outcome().is_some() {
+        return Err(BoardError::GameOver);
+    }
+    let mut search = Search::new(None, SearchOptions::default());
+    let mut positions = game.positions().clone();
+    let first_to_move = game.turn() == Player::First;
+    let (score, mov) = search.popout(game.board(), first_to_move, depth, i32::MIN+2, i32::MAX-2, solver,
+                                     &mut positions);
+    Ok(PopOutResult {
         score,
         mov,
-        nodes_searched,
-        end_in
+        nodes_searched: search.num_nodes,
+    })
+}
+
+/// Iterative deepening search, yielding the result of every completed depth.
+/// The search stops early as soon as the outcome of the game is known.
+/// The number of searched nodes includes all previous depths.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct SearchIter {
+    /// Keeps the line length of the variant
+    start: JsBoard,
+    solver: SolverType,
+    options: SearchOptions,
+    /// Only set for the standard board, see SearchIter::with_database()
+    database: Option<Arc<EndgameDatabase>>,
+    /// Depth of the last yielded result
+    depth: u8,
+    max_depth: u8,
+    num_nodes: u64,
+    finished: bool,
+}
+
+#[wasm_bindgen]
+impl SearchIter {
+    /// Fails if the game is already over
+    #[wasm_bindgen(constructor)]
+    pub fn js_new(start: &JsBoard, max_depth: u8, solver: SolverType) -> Result<SearchIter, BoardError> {
+        SearchIter::js_with_options(start, max_depth, solver, &SearchOptions::default())
+    }
+
+    #[wasm_bindgen(js_name = with_options)]
+    pub fn js_with_options(start: &JsBoard, max_depth: u8, solver: SolverType, options: &SearchOptions)
+        -> Result<SearchIter, BoardError> {
+        with_variant!(*start, board => check_not_over(&board))?;
+        Ok(SearchIter {
+            start: *start,
+            solver,
+            options: *options,
+            database: None,
+            depth: 0,
+            max_depth: u8::min(max_depth, 42 - start.number_of_stones() as u8),
+            num_nodes: 0,
+            finished: false,
+        })
+    }
+
+    /// Depth of the last returned result
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Searches the next depth. Returns undefined if the search is finished
+    pub fn next_result(&mut self) -> Option<SolveResult> {
+        self.next()
+    }
+}
+
+impl SearchIter {
+    /// Fails if the game is already over
+    pub fn new(start: &BitBoard, max_depth: u8, solver: SolverType) -> Result<SearchIter, BoardError> {
+        SearchIter::with_options(start, max_depth, solver, &SearchOptions::default())
+    }
+
+    pub fn with_options(start: &BitBoard, max_depth: u8, solver: SolverType, options: &SearchOptions)
+        -> Result<SearchIter, BoardError> {
+        SearchIter::js_with_options(&JsBoard::from(*start), max_depth, solver, options)
+    }
+
+    /// Same as new, but uses the exact results of the endgame database where available
+    pub fn with_database(start: &BitBoard, max_depth: u8, solver: SolverType, database: Arc<EndgameDatabase>)
+        -> Result<SearchIter, BoardError> {
+        Ok(SearchIter {
+            database: Some(database),
+            ..SearchIter::new(start, max_depth, solver)?
+        })
     }
-    // return SolveResult::new(0,0)
+}
+
+impl Iterator for SearchIter {
+    type Item = SolveResult;
+
+    fn next(&mut self) -> Option<SolveResult> {
+        if self.finished || self.depth >= self.max_depth {
+            return None;
+        }
+        self.depth += 1;
+
+        let mut search = Search::new(self.database.as_deref(), self.options);
+        let mut result = with_variant!(self.start, board => search.solve(&board, self.depth, self.solver));
+        self.num_nodes += result.nodes_searched;
+        result.nodes_searched = self.num_nodes;
+
+        // A win or loss can't change with more depth
+        self.finished = match self.solver {
+            SolverType::Strong => result.score.abs() >= 98,
+            SolverType::Weak => result.score != 0,
+        };
+        Some(result)
+    }
+}
+
+/// There is nothing to search if a player has won or the board is full
+fn check_not_over<V: Variant>(start: &V) -> Result<(), BoardError> {
+    if start.has_won() || start.has_lost() || start.number_of_stones() as usize == V::WIDTH * V::HEIGHT {
+        return Err(BoardError::GameOver);
+    }
+    Ok(())
+}
+
+/// Columns from the center to the edges, alternating between left and right
+#[inline]
+fn search_order(width: usize) -> impl Iterator<Item = u64> + Clone {
+    (0..width).map(move |i| {
+        let offset = i.div_ceil(2);
+        (if i % 2 == 1 { width / 2 - offset } else { width / 2 + offset }) as u64
+    })
 }
 
 /// Chooses the first out of multiple possible moves
-fn choose_move(moves: u64) -> u64 {
-    let whitespace = moves.trailing_zeros();
-    let mask = 1<< whitespace;
+fn choose_move<B: Bits>(moves: B) -> B {
+    let whitespace = moves.trailing_zeros() as usize;
+    let mask = B::ONE << whitespace;
     moves & mask
 }
+
 /// Solves the board using a strong solver BitBoard::is_winning_board()
 /// return score, best_move
-pub fn solve_strong(start: BitBoard, depth: u8, mut alpha: i32, mut beta: i32, num_nodes: &mut u64) -> (i32, u64) {
-    if start.has_lost() {
-        // 100 as a high value to differentiate a guaranteed win from the heuristic
-        return (-100 - depth as i32, 0);
+pub fn solve_strong<V: Variant>(start: V, depth: u8, alpha: i32, beta: i32, num_nodes: &mut u64) -> (i32, V::Bits) {
+    solve_strong_with_options(start, depth, alpha, beta, &SearchOptions::default(), num_nodes)
+}
+
+/// Same as solve_strong, but configures the search
+pub fn solve_strong_with_options<V: Variant>(start: V, depth: u8, alpha: i32, beta: i32, options: &SearchOptions,
+                                             num_nodes: &mut u64) -> (i32, V::Bits) {
+    let mut search = Search::new(None, *options);
+    let result = search.strong(start, depth, alpha, beta, options.max_extensions);
+    *num_nodes += search.num_nodes;
+    result
+}
+
+/// Solves the board using a weak solver BitBoard::is_winning_board()
+/// return score, best_move
+pub fn solve_weak<V: Variant>(start: V, depth: u8, alpha: i32, beta: i32, num_nodes: &mut u64) -> (i32, V::Bits) {
+    solve_weak_with_options(start, depth, alpha, beta, &SearchOptions::default(), num_nodes)
+}
+
+/// Same as solve_weak, but configures the search
+pub fn solve_weak_with_options<V: Variant>(start: V, depth: u8, alpha: i32, beta: i32, options: &SearchOptions,
+                                           num_nodes: &mut u64) -> (i32, V::Bits) {
+    let mut search = Search::new(None, *options);
+    let result = search.weak(start, depth, alpha, beta);
+    *num_nodes += search.num_nodes;
+    result
+}
+
+/// State of a single search
+struct Search<'a> {
+    num_nodes: u64,
+    database: Option<&'a EndgameDatabase>,
+    options: SearchOptions,
+}
+
+impl<'a> Search<'a> {
+    fn new(database: Option<&'a EndgameDatabase>, options: SearchOptions) -> Search<'a> {
+        Search {
+            num_nodes: 0,
+            database,
+            options,
+        }
     }
-    *num_nodes += 1;
 
-    // No conclusion found --> draw
-    if depth == 0 {
-        let score = start.heuristic3();
-        return (score, 0);
+    fn solve<V: Variant>(&mut self, start: &V, depth: u8, solver: SolverType) -> SolveResult {
+        let start = *start;
+        let depth = u8::min(depth, (V::WIDTH * V::HEIGHT) as u8 - start.number_of_stones() as u8);
+
+        let database_result = self.database
+            .zip(start.as_standard())
+            .and_then(|(database, board)| database.best_move(&board));
+        let (score, mov) = match (database_result, &solver) {
+            (Some((plies, mov)), SolverType::Strong) => (strong_score(plies, depth), V::Bits::from_mask(mov as u128)),
+            (Some((plies, mov)), SolverType::Weak) => (weak_score(plies, depth), V::Bits::from_mask(mov as u128)),
+            (None, SolverType::Strong) => self.strong(start, depth, i32::MIN+2, i32::MAX-2, self.options.max_extensions),
+            (None, SolverType::Weak) => self.weak(start, depth, i32::MIN+2, i32::MAX-2),
+        };
+
+        let end_in = match solver {
+            SolverType::Strong => {0}
+            SolverType::Weak => {
+                if score == 0 {
+                    0
+                } else {
+                    depth as i32 - score.abs() + 1
+                }
+            }
+        };
+        SolveResult {
+            nodes_searched: self.num_nodes,
+            end_in,
+            ..SolveResult::on_board::<V>(score, mov)
+        }
     }
 
-    let mut max_score = i32::MIN;
-    let mut best_move: u64 = 0;
+    /// Plies until the game is decided, if the position is stored in the database
+    #[inline]
+    fn lookup<V: Variant>(&self, board: &V) -> Option<i32> {
+        self.database.and_then(|database| database.lookup(&board.as_standard()?))
+    }
 
-    let forced = start.forced_moves();
-    if forced > 0 {
-        let moves = forced.count_ones();
-        if moves >= 2 {
-            // Game is lost. Only one spot can be taken this turn
-            return (-99 -(depth as i32), choose_move(forced));
+    /// Extensions is the number of plies that may still be searched beyond the depth, to follow
+    /// forced sequences.
+    fn strong<V: Variant>(&mut self, start: V, depth: u8, mut alpha: i32, beta: i32, extensions: u8) -> (i32, V::Bits) {
+        if start.has_lost() {
+            // 100 as a high value to differentiate a guaranteed win from the heuristic
+            return (-100 - depth as i32, V::Bits::ZERO);
         }
-        let new_board = start.play_field(forced);
-        let (score, _) = solve_weak(new_board, depth - 1, -beta, -alpha, num_nodes);
-        let score = -score;
-        best_move = forced;
-        max_score = score;
-
-        alpha = i32::max(alpha, score);
-
-        if alpha >= beta {
-            // Cutoff!
-            // break;
-            // Do nothing now
+        if V::MISERE && start.has_won() {
+            // The opponent completed a line
+            return (100 + depth as i32, V::Bits::ZERO);
+        }
+        self.num_nodes += 1;
+
+        if let Some(plies) = self.lookup(&start) {
+            return (strong_score(plies, depth), V::Bits::ZERO);
         }
-    } else {
-        let possible_moves = start.all_possible_moves();
 
-        for i in &SEARCH_ORDER {
-            let i = *i;
-            let to_play = BitBoard::move_in_row(possible_moves, i);
+        // No conclusion found --> draw.
+        // Threats are resolved first, the heuristic can't judge them
+        if depth == 0 && (extensions == 0 || start.forced_moves() == V::Bits::ZERO) {
+            let score = start.heuristic3();
+            return (score, V::Bits::ZERO);
+        }
+
+        let winning = start.winning_moves();
+        if winning != V::Bits::ZERO {
+            // Same score as the has_lost() check one ply deeper
+            return (99 + depth as i32, choose_move(winning));
+        }
+
+        let possible_moves = start.possible_non_losing_moves();
+        if possible_moves == V::Bits::ZERO {
+            let all_moves = start.all_possible_moves();
+            if all_moves == V::Bits::ZERO {
+                // Board is full
+                return (0, V::Bits::ZERO);
+            }
+            if V::MISERE {
+                // Every move completes a line. Same score as the has_won() check one ply deeper
+                return (-99 - depth as i32, choose_move(all_moves));
+            }
+            // Game is lost. The opponent wins with his next move
+            let forced = start.forced_moves();
+            let to_block = if forced != V::Bits::ZERO { forced } else { all_moves };
+            return (-98 - depth as i32, choose_move(to_block));
+        }
+
+        let mut max_score = i32::MIN;
+        let mut best_move = V::Bits::ZERO;
+
+        let forced = start.forced_moves();
+        if forced != V::Bits::ZERO {
+            // The threat has to be blocked. possible_moves only contains the blocking move.
+            // The forced move doesn't count towards the depth while extensions are left
+            let new_board = start.play_field(possible_moves);
+            let (score, _) = if extensions > 0 {
+                self.strong(new_board, depth, -beta, -alpha, extensions - 1)
+            } else {
+                self.strong(new_board, depth - 1, -beta, -alpha, 0)
+            };
+            let score = -score;
+            best_move = possible_moves;
+            max_score = score;
+        } else {
+            let mut searched_moves = 0;
+            for i in search_order(V::WIDTH) {
+                let to_play = V::move_in_row(possible_moves, i);
+
+                // No valid move
+                if to_play == V::Bits::ZERO {
+                    continue;
+                }
+
+                let new_board = start.play_field(to_play);
+                // Moves creating a threat are never reduced
+                let reduce = self.options.late_move_reductions && searched_moves >= FULL_DEPTH_MOVES
+                    && depth >= REDUCTION_MIN_DEPTH && new_board.forced_moves() == V::Bits::ZERO;
+                searched_moves += 1;
+
+                let score = if reduce {
+                    // Only test whether the move beats alpha
+                    let (score, _) = self.strong(new_board, depth - 2, -alpha - 1, -alpha, extensions);
+                    -score
+                } else {
+                    i32::MAX
+                };
+                let score = if score > alpha {
+                    let (score, _) = self.strong(new_board, depth - 1, -beta, -alpha, extensions);
+                    -score
+                } else {
+                    score
+                };
+
+                if score > max_score {
+                    max_score = score;
+                    best_move = to_play;
+                }
+                alpha = i32::max(alpha, score);
+
+                if alpha >= beta {
+                    // Cutoff!
+                    break;
+                }
+            }
+        }
+
+        (max_score, best_move)
+    }
+
+    fn weak<V: Variant>(&mut self, start: V, depth: u8, mut alpha: i32, beta: i32) -> (i32, V::Bits) {
+        if start.has_lost() {
+            return (-1 - depth as i32, V::Bits::ZERO);
+        }
+        if V::MISERE && start.has_won() {
+            // The opponent completed a line
+            return (1 + depth as i32, V::Bits::ZERO);
+        }
+        self.num_nodes += 1;
+
+        // No conclusion found --> draw
+        if depth == 0 {
+            return (0, V::Bits::ZERO);
+        }
+
+        if let Some(plies) = self.lookup(&start) {
+            return (weak_score(plies, depth), V::Bits::ZERO);
+        }
+
+        let winning = start.winning_moves();
+        if winning != V::Bits::ZERO {
+            // Same score as the has_lost() check one ply deeper
+            return (depth as i32, choose_move(winning));
+        }
+
+        let possible_moves = start.possible_non_losing_moves();
+        if possible_moves == V::Bits::ZERO {
+            let all_moves = start.all_possible_moves();
+            if all_moves == V::Bits::ZERO {
+                // Board is full
+                return (0, V::Bits::ZERO);
+            }
+            if V::MISERE {
+                // Every move completes a line. Same score as the has_won() check one ply deeper
+                return (-(depth as i32), choose_move(all_moves));
+            }
+            // Game is lost. The opponent wins with his next move
+            let forced = start.forced_moves();
+            let to_block = if forced != V::Bits::ZERO { forced } else { all_moves };
+            return (1 - depth as i32, choose_move(to_block));
+        }
+
+        // The second player can at least draw, so this position can not reach alpha
+        let proven = || start.as_standard().and_then(|board| knowledge::prove(&board)).is_some();
+        if self.options.knowledge_cutoffs && alpha >= 0 && depth >= KNOWLEDGE_MIN_DEPTH && proven() {
+            return (0, choose_move(possible_moves));
+        }
+
+        let mut max_score = i32::MIN;
+        let mut best_move = V::Bits::ZERO;
+
+        for i in search_order(V::WIDTH) {
+            let to_play = V::move_in_row(possible_moves, i);
 
             // No valid move
-            if to_play == 0 {
+            if to_play == V::Bits::ZERO {
                 continue;
             }
 
             let new_board = start.play_field(to_play);
-            let (score, _) = solve_strong(new_board, depth - 1, -beta, -alpha, num_nodes);
+            let (score, _) = self.weak(new_board, depth - 1, -beta, -alpha);
             let score = -score;
 
             if score > max_score {
@@ -149,66 +571,63 @@ pub fn solve_strong(start: BitBoard, depth: u8, mut alpha: i32, mut beta: i32, n
                 break;
             }
         }
-    }
 
-    (max_score, best_move)
-}
-/// Solves the board using a weak solver BitBoard::is_winning_board()
-/// return score, best_move
-pub fn solve_weak(start: BitBoard, depth: u8, mut alpha: i32, mut beta: i32, num_nodes: &mut u64) -> (i32, u64) {
-    if start.has_lost() {
-        return (-1 - depth as i32, 0);
+        (max_score, best_move)
     }
-    *num_nodes += 1;
 
-    // No conclusion found --> draw
-    if depth == 0 {
-        return (0, 0);
-    }
-
-    let mut max_score = i32::MIN;
-    let mut best_move: u64 = 0;
+    /// Searches all drops and pops of PopOut. Positions counts the occurrences of every
+    /// position in the game and the searched line, see popout::position_key()
+    #[allow(clippy::too_many_arguments)]
+    fn popout(&mut self, start: BitBoard, first_to_move: bool, depth: u8, mut alpha: i32, beta: i32,
+              solver: SolverType, positions: &mut HashMap<u64, u8>) -> (i32, Option<PopOutMove>) {
+        self.num_nodes += 1;
 
-    let forced = start.forced_moves();
-    if forced > 0 {
-        let moves = forced.count_ones();
-        if moves >= 2 {
-            // Game is lost. Only one spot can be taken this turn
-            return (- (depth as i32), choose_move(forced));
-        }
-        let new_board = start.play_field(forced);
-        let (score, _) =  solve_weak(new_board, depth-1, -beta, -alpha, num_nodes);
-        let score = -score;
-
-        alpha = i32::max(alpha, score);
-        best_move = forced;
-        max_score = score;
-
-        if alpha >= beta {
-            // Cutoff!
-            // break;
-            // Do nothing now
+        // No conclusion found --> draw or the heuristic
+        if depth == 0 {
+            let score = match solver {
+                SolverType::Strong => start.heuristic3(),
+                SolverType::Weak => 0,
+            };
+            return (score, None);
         }
+        // Same scores as a winning move of the weak and strong search
+        let win = match solver {
+            SolverType::Strong => 99 + depth as i32,
+            SolverType::Weak => depth as i32,
+        };
 
-    } else {
-        let possible_moves = start.all_possible_moves();
+        let mut max_score = i32::MIN;
+        let mut best_move = None;
 
-        for i in &SEARCH_ORDER {
-            let i = *i;
-            let to_play = BitBoard::move_in_row(possible_moves, i);
+        let order = search_order(BOARD_WIDTH);
+        let drops = order.clone().map(|column| PopOutMove::drop(column as u8));
+        let pops = order.map(|column| PopOutMove::pop(column as u8));
+        for mov in drops.chain(pops) {
+            let board = match mov.play(&start) {
+                Ok(board) => board,
+                Err(_) => continue,
+            };
 
-            // No valid move
-            if to_play == 0 {
-                continue;
-            }
-
-            let new_board = start.play_field(to_play);
-            let (score, _) = solve_weak(new_board, depth - 1, -beta, -alpha, num_nodes);
-            let score = -score;
+            let score = match popout::decided(&board) {
+                Some(result) => -result * win,
+                None => {
+                    let key = position_key(&board, !first_to_move);
+                    let occurrences = positions.entry(key).or_insert(0);
+                    if *occurrences + 1 >= REPETITIONS {
+                        0
+                    } else {
+                        *occurrences += 1;
+                        let (score, _) = self.popout(board, !first_to_move, depth - 1, -beta, -alpha, solver,
+                                                     positions);
+                        *positions.entry(key).or_insert(1) -= 1;
+                        -score
+                    }
+                }
+            };
 
             if score > max_score {
                 max_score = score;
-                best_move = to_play;
+                best_move = Some(mov);
             }
             alpha = i32::max(alpha, score);
 
@@ -217,16 +636,49 @@ pub fn solve_weak(start: BitBoard, depth: u8, mut alpha: i32, mut beta: i32, num
                 break;
             }
         }
+
+        if best_move.is_none() {
+            // A full board without an own stone at the bottom
+            return (0, None);
+        }
+        (max_score, best_move)
     }
+}
 
-    (max_score, best_move)
+/// Converts an exact result of the endgame database to the score of solve_weak.
+/// Results beyond the search depth are scored as draw, just like the search does.
+fn weak_score(plies: i32, depth: u8) -> i32 {
+    let depth = depth as i32;
+    if plies == 0 || plies.abs() > depth {
+        0
+    } else if plies > 0 {
+        depth - plies + 1
+    } else {
+        -plies - 1 - depth
+    }
+}
+
+/// Converts an exact result of the endgame database to the score of solve_strong
+fn strong_score(plies: i32, depth: u8) -> i32 {
+    let depth = depth as i32;
+    if plies > 0 {
+        100 + depth - plies
+    } else if plies < 0 {
+        -plies - 100 - depth
+    } else {
+        0
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::board::BitBoard;
-    use crate::engine::{solve_weak, solve};
+    use crate::board::{BitBoard, Board, BoardError, FieldType, JsBoard, Move};
+    use crate::endgame::EndgameDatabase;
+    use crate::popout::{MoveKind::{Drop, Pop}, PopOut, PopOutMove};
+    use crate::engine::{js_solve, solve_popout, solve_weak, solve_weak_with_options, solve, solve_with_database, solve_with_options,
+                        SearchIter, SearchOptions};
     use crate::engine::SolverType::{Weak, Strong};
+    use std::sync::Arc;
 
     #[test]
     fn test_solve_easy() {
@@ -239,7 +691,7 @@ mod tests {
             nnncnnn";
         let bits = BitBoard::from_string(board_easy).unwrap();
 
-        let result = solve(&bits, 3, Weak);
+        let result = solve(&bits, 3, Weak).unwrap();
 
         let best_move_easy =
             "nnnnnnn
@@ -248,14 +700,395 @@ mod tests {
             nnnnnnn
             nnnpnnn
             nnnnnnn";
-        let best_move_easy = BitBoard::from_string(best_move_easy).unwrap().get_player_bit_repr();
+        let best_move_easy = BitBoard::mask_from_string(best_move_easy).unwrap();
 
         println!("{}", result.score);
         assert_eq!(best_move_easy, result.mov)
     }
 
-    // #[test]
+    #[test]
+    fn test_solve_immediate_win() {
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            npnnnnn
+            npnnnnc
+            npnnncc";
+        let bits = BitBoard::from_string(board).unwrap();
+
+        let best_move =
+            "nnnnnnn
+            nnnnnnn
+            npnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn";
+        let best_move = BitBoard::mask_from_string(best_move).unwrap();
+
+        let result = solve(&bits, 8, Weak).unwrap();
+        assert_eq!(result.mov, best_move);
+        assert_eq!(result.best_move(), Some(Move::from_coordinates(1, 3).unwrap()));
+        assert_eq!((result.column, result.row), (Some(1), Some(3)));
+        assert_eq!(result.end_in, 1);
+        assert_eq!(result.nodes_searched, 1);
+
+        let result = solve(&bits, 8, Strong).unwrap();
+        assert_eq!(result.mov, best_move);
+        assert_eq!(result.nodes_searched, 1);
+    }
+
+    #[test]
+    fn test_solve_game_over() {
+        let won = BitBoard::from_moves("1212121").unwrap();
+        assert_eq!(solve(&won, 8, Weak), Err(BoardError::GameOver));
+        assert_eq!(SearchIter::new(&won, 8, Strong).err(), Some(BoardError::GameOver));
+    }
+
+    #[test]
+    fn test_solve_double_threat() {
+        // The opponent threatens to win at both ends of the bottom row
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnppnnn
+            nncccnn";
+        let bits = BitBoard::from_string(board).unwrap();
+
+        let result = solve(&bits, 8, Weak).unwrap();
+        assert!(result.score < 0);
+        assert_eq!(result.end_in, 2);
+
+        // Same result as the search without the shortcut: Play a move and lose one ply later
+        let mut num_nodes = 0;
+        let (score, _) = solve_weak(bits.play_field(1 << 8), 7, i32::MIN + 2, i32::MAX - 2, &mut num_nodes);
+        assert_eq!(-score, result.score);
+    }
+
+    #[test]
+    fn test_solve_strong_extensions() {
+        // Playing column 1 or 4 creates a double threat. Depth 1 only searches this move,
+        // the extension shows that the opponent can't block both threats
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnc
+            nnppnnc";
+        let bits = BitBoard::from_string(board).unwrap();
+
+        let result = solve(&bits, 1, Strong).unwrap();
+        assert!(result.score >= 98);
+        assert!(result.column == Some(4) || result.column == Some(1));
+    }
+
+    #[test]
+    fn test_solve_board_sizes() {
+        // Both small boards are a draw with perfect play
+        for result in [solve(&Board::<5, 4>::empty(), 20, Weak), solve(&Board::<4, 5>::empty(), 20, Weak)] {
+            assert_eq!(result.map(|result| result.score), Ok(0));
+        }
+
+        // Only the last column completes the four
+        let bits = Board::<8, 7>::from_moves("546677").unwrap();
+        let result = solve(&bits, 3, Weak).unwrap();
+        assert_eq!((result.score, result.column, result.row), (3, Some(7), Some(0)));
+        let result = solve(&bits, 5, Strong).unwrap();
+        assert!(result.score >= 98);
+        assert_eq!(result.column, Some(7));
+
+        // The open three in the bottom row can't be stopped
+        let bits = Board::<6, 5>::from_moves("22334").unwrap();
+        let result = solve(&bits, 6, Weak).unwrap();
+        assert!(result.score < 0);
+    }
+
+    #[test]
+    fn test_solve_line_lengths() {
+        // The first player wins Connect Three with two open sides in the bottom row
+        let result = solve(&Board::<7, 6, u64, 3>::empty(), 9, Weak).unwrap();
+        assert!(result.score > 0);
+
+        // Four in a row don't end Connect Five
+        let bits = Board::<7, 6, u64, 5>::from_moves("1122334").unwrap();
+        let result = solve(&bits, 3, Weak).unwrap();
+        assert_eq!((result.score, result.column), (0, Some(4)));
+
+        let board = JsBoard::connect(5).unwrap();
+        let board = "1122334".chars().enumerate().fold(board, |board, (index, column)| {
+            let player = if index % 2 == 0 { FieldType::Opponent } else { FieldType::Player };
+            board.play_column(column.to_digit(10).unwrap() as u8 - 1, player).unwrap()
+        });
+        assert!(!board.has_lost());
+        assert_eq!(js_solve(&board, 3, Weak).unwrap().column, Some(4));
+        let mut search = SearchIter::js_new(&JsBoard::connect(3).unwrap(), 5, Weak).unwrap();
+        assert!(search.next_result().unwrap().score >= 0);
+        assert_eq!(JsBoard::connect(7), Err(BoardError::InvalidLineLength { length: 7 }));
+    }
+
+    #[test]
+    fn test_solve_popout() {
+        // Popping the first column completes four for both players, which wins
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            pnnnnnn
+            cpppnnn
+            pcccnnc";
+        let game = PopOut::from_board(BitBoard::from_string(board).unwrap());
+        let result = solve_popout(&game, 3, Weak).unwrap();
+        assert_eq!((result.score, result.mov), (3, Some(PopOutMove::pop(0))));
+        let result = solve_popout(&game, 3, Strong).unwrap();
+        assert_eq!(result.mov, Some(PopOutMove::pop(0)));
+
+        // Popping the first column completes only the four of the opponent.
+        // The threat in the bottom row has to be blocked instead
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            cpppnnn
+            pcccnnn";
+        let game = PopOut::from_board(BitBoard::from_string(board).unwrap());
+        let result = solve_popout(&game, 2, Weak).unwrap();
+        assert_eq!((result.score, result.mov), (0, Some(PopOutMove::drop(4))));
+
+        // The opponent has two threats, but popping the first column
+        // repeats the position after it for the third time
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            pncccnn";
+        // Too many stones of the opponent, only reachable by popping
+        let board = BitBoard::from_string_unchecked(board).unwrap();
+        let result = solve_popout(&PopOut::from_board(board), 2, Weak).unwrap();
+        assert!(result.score < 0);
+        let mut game = PopOut::from_board(board);
+        for (kind, column) in [(Pop, 0), (Drop, 0), (Drop, 6), (Pop, 0), (Pop, 6), (Drop, 6), (Drop, 0), (Pop, 6)] {
+            game.play(PopOutMove { kind, column }).unwrap();
+        }
+        assert_eq!(game.board(), board);
+        let result = solve_popout(&game, 2, Weak).unwrap();
+        assert_eq!((result.score, result.mov), (0, Some(PopOutMove::pop(0))));
+    }
+
+    #[test]
+    fn test_solve_cylindrical() {
+        // Both ends of the wrapped around three in the bottom row can't be blocked
+        let bits = Board::<7, 6, u64, 4, true>::from_moves("64741").unwrap();
+        let result = solve(&bits, 4, Weak).unwrap();
+        assert!(result.score < 0);
+        let result = solve(&BitBoard::from_moves("64741").unwrap(), 4, Weak).unwrap();
+        assert_eq!(result.score, 0);
+
+        let bits = Board::<7, 6, u64, 4, true>::from_moves("647413").unwrap();
+        let result = solve(&bits, 3, Weak).unwrap();
+        assert_eq!((result.score, result.column), (3, Some(1)));
+        let result = solve(&bits, 3, Strong).unwrap();
+        assert!(result.score >= 98);
+    }
+
+    #[test]
+    fn test_solve_misere() {
+        // The first player must not complete the first column
+        let board = Board::<7, 6, u64, 4, false, true>::from_moves("121212").unwrap();
+        let result = solve(&board, 2, Weak).unwrap();
+        assert_eq!(result.score, 0);
+        assert_ne!(result.column, Some(0));
+        assert!(solve(&board.play_column(0, FieldType::Player).unwrap(), 2, Weak).is_err());
+
+        // Every move completes three in a row. The player to move has one stone more than the
+        // opponent, which can not occur in a game
+        let board = Board::<4, 1, u64, 3, false, true>::from_string_unchecked("nppc").unwrap();
+        let result = solve(&board, 1, Weak).unwrap();
+        assert_eq!((result.score, result.column), (-1, Some(0)));
+        assert!(solve(&board, 1, Strong).unwrap().score <= -98);
+
+        // Both moves leave the opponent only his own line
+        let board = Board::<5, 1, u64, 3, false, true>::from_string("pnccn").unwrap();
+        let result = solve(&board, 2, Weak).unwrap();
+        assert_eq!((result.score, result.end_in), (1, 2));
+        assert!(solve(&board, 2, Strong).unwrap().score >= 98);
+
+        let js_board = JsBoard::variant(4, true).unwrap();
+        let js_board = "121212".chars().fold(js_board, |board, c| {
+            let column = c.to_digit(10).unwrap() as u8 - 1;
+            let player = if board.number_of_stones() % 2 == 0 { FieldType::Player } else { FieldType::Opponent };
+            board.play_column(column, player).unwrap()
+        });
+        assert_ne!(js_solve(&js_board, 2, Weak).unwrap().column, Some(0));
+        assert!(js_board.play_column(0, FieldType::Player).unwrap().has_lost());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        use crate::engine::SolveResult;
+
+        let result = SolveResult { score: 3, mov: 1 << 8, column: Some(1), row: Some(1), nodes_searched: 42, end_in: 2 };
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(json, r#"{"score":3,"mov":256,"column":1,"row":1,"nodes_searched":42,"end_in":2}"#);
+        assert_eq!(serde_json::from_str::<SolveResult>(&json).unwrap(), result);
+
+        let result = solve(&BitBoard::from_moves("4444").unwrap(), 2, Weak).unwrap();
+        let restored: SolveResult = serde_json::from_str(&serde_json::to_string(&result).unwrap()).unwrap();
+        assert_eq!(restored, result);
+        assert_eq!(serde_json::to_string(&SolveResult::default()).unwrap(),
+                   r#"{"score":0,"mov":0,"column":null,"row":null,"nodes_searched":0,"end_in":0}"#);
+    }
+
+    #[test]
+    fn test_solve_large_boards() {
+        // The winning move lies beyond the first 64 bits
+        let bits = Board::<9, 7, u128>::from_moves("657788").unwrap();
+        let result = solve(&bits, 3, Weak).unwrap();
+        assert_eq!((result.score, result.column, result.row, result.mov), (3, Some(8), Some(0), 0));
+
+        let bits = Board::<10, 8, u128>::from_moves("768899").unwrap();
+        let result = solve(&bits, 5, Strong).unwrap();
+        assert!(result.score >= 98);
+        assert_eq!(result.column, Some(9));
+
+        // The open three in the bottom row can't be stopped
+        let bits = Board::<10, 8, u128>::from_moves("71819").unwrap();
+        let result = solve(&bits, 4, Weak).unwrap();
+        assert!(result.score < 0);
+    }
+
+    #[test]
+    fn test_solve_late_move_reductions() {
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnncnnn";
+        let bits = BitBoard::from_string(board).unwrap();
+
+        let full_depth = solve_with_options(&bits, 9, Strong, &SearchOptions::default()).unwrap();
+        let options = SearchOptions {
+            late_move_reductions: true,
+            ..SearchOptions::default()
+        };
+        let reduced = solve_with_options(&bits, 9, Strong, &options).unwrap();
+        assert_eq!(reduced.mov, full_depth.mov);
+        assert!(reduced.nodes_searched < full_depth.nodes_searched);
+    }
+
+    #[test]
+    fn test_solve_knowledge_cutoffs() {
+        // The knowledge based rules prove that the first player (to move) can't win
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            pnncnnp
+            cnnppcc
+            ppncccp
+            cpnpccp";
+        let bits = BitBoard::from_string(board).unwrap();
+        let options = SearchOptions {
+            knowledge_cutoffs: true,
+            ..SearchOptions::default()
+        };
+
+        // Null window search whether the first player wins
+        let mut num_nodes = 0;
+        let (score, mov) = solve_weak_with_options(bits, 24, 0, 1, &options, &mut num_nodes);
+        assert_eq!(score, 0);
+        assert!(mov & bits.possible_non_losing_moves() > 0);
+        assert_eq!(num_nodes, 1);
+
+        let mut num_nodes = 0;
+        let (score, _) = solve_weak(bits, 24, 0, 1, &mut num_nodes);
+        assert!(score <= 0);
+        assert!(num_nodes > 1);
+    }
+
+    #[test]
+    fn test_search_iter() {
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnncnnn";
+        let bits = BitBoard::from_string(board).unwrap();
+
+        let mut search = SearchIter::new(&bits, 6, Strong).unwrap();
+        let results: Vec<_> = search.by_ref().collect();
+        assert_eq!(results.len(), 6);
+        assert_eq!(search.depth(), 6);
+        assert!(results.windows(2).all(|r| r[0].nodes_searched < r[1].nodes_searched));
+
+        // Every result is the same as a search with that depth
+        let last = solve(&bits, 6, Strong).unwrap();
+        assert_eq!(results[5].score, last.score);
+        assert_eq!(results[5].mov, last.mov);
+    }
+
+    #[test]
+    fn test_search_iter_stops_at_win() {
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnc
+            nnppnnc";
+        let bits = BitBoard::from_string(board).unwrap();
+
+        // The double threat is found at depth 3. Deeper searches can't change the outcome
+        let results: Vec<_> = SearchIter::new(&bits, 10, Weak).unwrap().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].score > 0);
+        assert_eq!(results[2].end_in, 3);
+    }
+
+    #[test]
+    fn test_solve_with_database() {
+        let board =
+            "ncnnnnc
+            nppnncc
+            nccnnpc
+            cppnccp
+            pcpppcp
+            ppccpcp";
+        let bits = BitBoard::from_string(board).unwrap();
+        let database = EndgameDatabase::generate(&bits, 34);
+
+        let result = solve(&bits, 42, Weak).unwrap();
+        let with_database = solve_with_database(&bits, 42, Weak, &database).unwrap();
+        assert_eq!(with_database.score, result.score);
+        assert_eq!(with_database.end_in, result.end_in);
+        assert!(with_database.nodes_searched < result.nodes_searched);
+
+        // Positions stored in the database are not searched at all
+        let database = EndgameDatabase::generate(&bits, 30);
+        let with_database = solve_with_database(&bits, 42, Weak, &database).unwrap();
+        assert_eq!(with_database.score, result.score);
+        assert_eq!(with_database.nodes_searched, 0);
+        assert!(with_database.mov & bits.all_possible_moves() > 0);
+
+        // The iterative deepening search consults the database at every depth
+        let iter = SearchIter::with_database(&bits, 42, Weak, Arc::new(database)).unwrap();
+        let last = iter.last().unwrap();
+        assert_eq!((last.end_in, last.mov), (with_database.end_in, with_database.mov));
+        assert_eq!(last.nodes_searched, 0);
+    }
+
+    #[test]
     fn test_solve_strong() {
+        // The only move that doesn't lose blocks the vertical three in the fifth column
         let board_easy =
             "nnnnnnn
             nnnnnnn
@@ -265,19 +1098,9 @@ mod tests {
             npcccpn";
         let bits = BitBoard::from_string(board_easy).unwrap();
 
-        let result = solve(&bits, 1, Strong);
-
-        let best_move_easy =
-            "nnnnnnn
-            nnnnnnn
-            nnnnnpn
-            nnnnnnn
-            nnnnnnn
-            nnnnnnn";
-        let best_move_easy = BitBoard::from_string(best_move_easy).unwrap().get_player_bit_repr();
+        let result = solve(&bits, 1, Strong).unwrap();
 
-        println!("Score: {}", result.score);
-        println!("Move: {}", result.mov);
+        assert_eq!((result.score, result.column, result.row), (-2, Some(4), Some(3)));
     }
 
 }
\ No newline at end of file
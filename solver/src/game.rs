@@ -0,0 +1,313 @@
+//! Connect Four games with move history
+//!
+//! A Game records the played columns from the empty board, so moves can be undone and redone
+//! and every position of the game can be replayed. The players are named by the order in
+//! which they move, unlike the FieldType of a board, which is relative to the player to move.
+
+use crate::board::{BitBoard, BoardError, JsBoard, BOARD_HEIGHT, BOARD_WIDTH};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Player {
+    First, Second
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Outcome {
+    FirstPlayerWins, SecondPlayerWins, Draw
+}
+
+/// A game with its move history. Undone moves are kept until a different move is played,
+/// so they can be redone.
+/// Serialized as GameRecord, the board is replayed from the moves.
+#[wasm_bindgen]
+#[derive(Default, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(try_from = "GameRecord", into = "GameRecord"))]
+pub struct Game {
+    board: BitBoard,
+    /// All played columns, including undone moves
+    moves: Vec<u8>,
+    /// Number of moves on the board
+    ply: usize,
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Game {
+        Game::default()
+    }
+
+    /// The current position
+    #[wasm_bindgen(js_name = board)]
+    pub fn js_board(&self) -> JsBoard {
+        self.board.into()
+    }
+
+    /// Columns of all moves on the board, starting at 0 on the left
+    pub fn moves(&self) -> Vec<u8> {
+        self.moves[..self.ply].to_vec()
+    }
+
+    /// The player to move
+    pub fn turn(&self) -> Player {
+        if self.ply.is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    /// Returns None while the game is not over
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.board.has_lost() {
+            // The player that made the last move won
+            match self.turn() {
+                Player::First => Some(Outcome::SecondPlayerWins),
+                Player::Second => Some(Outcome::FirstPlayerWins),
+            }
+        } else if self.ply == BOARD_WIDTH * BOARD_HEIGHT {
+            Some(Outcome::Draw)
+        } else {
+            None
+        }
+    }
+
+    /// Plays a stone of the player to move into the column.
+    /// Undone moves can't be redone afterwards.
+    pub fn play(&mut self, column: u8) -> Result<(), BoardError> {
+        if self.outcome().is_some() {
+            return Err(BoardError::GameOver);
+        }
+        if column as usize >= BOARD_WIDTH {
+            return Err(BoardError::ColumnOutOfRange { column });
+        }
+        let mov = BitBoard::move_in_row(self.board.all_possible_moves(), column as u64);
+        if mov == 0 {
+            return Err(BoardError::ColumnFull { column });
+        }
+
+        self.board = self.board.play_field(mov);
+        self.moves.truncate(self.ply);
+        self.moves.push(column);
+        self.ply += 1;
+        Ok(())
+    }
+
+    /// Takes back the last move. Returns false if there is no move to undo
+    pub fn undo(&mut self) -> bool {
+        if self.ply == 0 {
+            return false;
+        }
+        self.ply -= 1;
+        self.board = self.replay(self.ply);
+        true
+    }
+
+    /// Plays the last undone move again. Returns false if there is no move to redo
+    pub fn redo(&mut self) -> bool {
+        if self.ply == self.moves.len() {
+            return false;
+        }
+        let mov = BitBoard::move_in_row(self.board.all_possible_moves(), self.moves[self.ply] as u64);
+        self.board = self.board.play_field(mov);
+        self.ply += 1;
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.ply > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.ply < self.moves.len()
+    }
+}
+
+impl Game {
+    /// The current position
+    pub fn board(&self) -> BitBoard {
+        self.board
+    }
+
+    /// Iterates over all positions of the game from the empty board up to the current position
+    pub fn positions(&self) -> impl Iterator<Item = BitBoard> + '_ {
+        let mut board = BitBoard::empty();
+        std::iter::once(board).chain(self.moves[..self.ply].iter().map(move |column| {
+            board = board.play_field(BitBoard::move_in_row(board.all_possible_moves(), *column as u64));
+            board
+        }))
+    }
+
+    /// Position after the given number of moves
+    fn replay(&self, ply: usize) -> BitBoard {
+        self.positions().nth(ply).unwrap_or_default()
+    }
+}
+
+/// The stored form of a Game
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct GameRecord {
+    /// All played columns starting at 0 on the left, including undone moves
+    moves: Vec<u8>,
+    /// Number of moves on the board
+    ply: usize,
+}
+
+#[cfg(feature = "serde")]
+impl From<Game> for GameRecord {
+    fn from(game: Game) -> GameRecord {
+        GameRecord { moves: game.moves, ply: game.ply }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<GameRecord> for Game {
+    type Error = BoardError;
+
+    fn try_from(record: GameRecord) -> Result<Game, BoardError> {
+        if record.ply > record.moves.len() {
+            return Err(BoardError::InvalidPly { ply: record.ply, moves: record.moves.len() });
+        }
+        let mut game = Game::new();
+        for column in &record.moves {
+            game.play(*column)?;
+        }
+        while game.ply > record.ply {
+            game.undo();
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{BitBoard, BoardError};
+    use crate::game::{Game, Outcome, Player};
+
+    #[test]
+    fn test_play() {
+        let mut game = Game::new();
+        assert_eq!(game.turn(), Player::First);
+
+        game.play(3).unwrap();
+        game.play(3).unwrap();
+        assert_eq!(game.turn(), Player::First);
+        assert_eq!(game.moves(), vec![3, 3]);
+
+        let expected =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnncnnn
+            nnnpnnn";
+        assert_eq!(game.board(), BitBoard::from_string(expected).unwrap());
+
+        assert_eq!(game.play(7), Err(BoardError::ColumnOutOfRange { column: 7 }));
+        for _ in 0..4 {
+            game.play(3).unwrap();
+        }
+        assert_eq!(game.play(3), Err(BoardError::ColumnFull { column: 3 }));
+        assert_eq!(game.moves().len(), 6);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut game = Game::new();
+        assert!(!game.undo());
+        game.play(3).unwrap();
+        game.play(4).unwrap();
+        let board = game.board();
+
+        assert!(game.undo());
+        assert_eq!(game.moves(), vec![3]);
+        assert_eq!(game.turn(), Player::Second);
+        assert!(game.redo());
+        assert_eq!(game.board(), board);
+        assert!(!game.redo());
+
+        // A new move discards the undone moves
+        game.undo();
+        game.play(2).unwrap();
+        assert!(!game.can_redo());
+        assert_eq!(game.moves(), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_outcome() {
+        let mut game = Game::new();
+        for column in [0, 1, 0, 1, 0, 1] {
+            game.play(column).unwrap();
+        }
+        assert_eq!(game.outcome(), None);
+        game.play(0).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::FirstPlayerWins));
+        assert_eq!(game.play(2), Err(BoardError::GameOver));
+
+        game.undo();
+        game.play(2).unwrap();
+        game.play(1).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::SecondPlayerWins));
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut game = Game::new();
+        // Fills the board column pair by column pair without any four
+        for columns in [[0, 1], [2, 3], [4, 5]] {
+            for i in 0..6 {
+                let offset = if i < 3 { 0 } else { 1 };
+                game.play(columns[offset]).unwrap();
+                game.play(columns[1 - offset]).unwrap();
+            }
+        }
+        for _ in 0..6 {
+            game.play(6).unwrap();
+        }
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut game = Game::new();
+        game.play(3).unwrap();
+        game.play(2).unwrap();
+
+        let positions: Vec<BitBoard> = game.positions().collect();
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions[0], BitBoard::empty());
+        assert_eq!(positions[2], game.board());
+        assert_eq!(positions[1].number_of_stones(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let mut game = Game::new();
+        for column in [3, 3, 4] {
+            game.play(column).unwrap();
+        }
+        game.undo();
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(json, r#"{"moves":[3,3,4],"ply":2}"#);
+        let restored: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, game);
+        assert!(restored.can_redo());
+
+        assert!(serde_json::from_str::<Game>(r#"{"moves":[3],"ply":2}"#).is_err());
+        // The moves have to be playable
+        assert!(serde_json::from_str::<Game>(r#"{"moves":[7],"ply":1}"#).is_err());
+        assert!(serde_json::from_str::<Game>(r#"{"moves":[0,1,0,1,0,1,0,1],"ply":8}"#).is_err());
+        assert_eq!(serde_json::to_string(&Outcome::Draw).unwrap(), r#""Draw""#);
+    }
+}
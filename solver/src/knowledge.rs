@@ -0,0 +1,719 @@
+//! Knowledge based rules that prove a position without searching the game tree
+//!
+//! Implements the strategic rules of VICTOR, described by Victor Allis in
+//! "A Knowledge-based Approach of Connect-Four" (1988). If the first player ("White")
+//! is to move, the second player ("Black") controls the zugzwang: By answering every move of
+//! White, Black decides which squares he gets. Each rule describes such a local answering
+//! scheme together with the White groups (possible fours) it refutes. If a set of compatible
+//! rules refutes every White group, White can not win and Black at least draws.
+//! If the answering scheme additionally gives Black every empty square of one of his own
+//! groups (an Aftereven group, completed by Claimevens), Black wins, as the game can only end
+//! with a full board or a four of Black.
+//!
+//! Every empty square has to be covered by exactly one answering scheme, so Black always has an
+//! answer to a move of White. Squares not used by any rule are answered by a follow-up in the
+//! same column (playing directly on top of White). This requires the unused squares of a column
+//! to come in pairs. Columns with an odd number of unused squares are paired with each other by
+//! additional Baseinverses on their lowest squares. Columns with an odd number of empty squares
+//! can also be answered with Verticals instead, leaving their top squares to be paired by
+//! Lowinverses.
+
+use crate::board::{BitBoard, BOARD_HEIGHT, BOARD_WIDTH};
+use std::fmt;
+
+/// Maximum number of rule combinations tried before giving up
+const MAX_SEARCH_STEPS: u32 = 20_000;
+const COLUMN_BITS: usize = BitBoard::COLUMN_BITS;
+
+/// The strategic rules of VICTOR
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Rule {
+    /// Black gets the upper of two empty squares, if the upper one is in an even row
+    Claimeven,
+    /// Black gets one of two directly playable squares
+    Baseinverse,
+    /// Black gets one of two empty squares directly above each other, if the upper one is odd.
+    /// The whole column has to be answered with Verticals
+    Vertical,
+    /// Black completes a group with Claimevens before White can play above it
+    Aftereven,
+    /// Two columns answered with Verticals, Black gets one of their top squares
+    Lowinverse,
+    /// Three empty squares in two columns each, Black gets one of the upper squares
+    Highinverse,
+    /// A Baseinverse and a Claimeven using three directly playable squares
+    Baseclaim,
+    /// Black gets a square below each square of a White group or completes his own group first
+    Before,
+    /// A Before that uses a Baseinverse on one of its directly playable squares
+    Specialbefore,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A single rule applied to the board
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Solution {
+    pub rule: Rule,
+    /// All squares used by the rule
+    pub squares: u64,
+    atoms: Vec<Atom>,
+    /// Indices of all groups refuted by this rule
+    refutes: u128,
+}
+
+/// A set of compatible rules refuting every White group
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Proof {
+    pub solutions: Vec<Solution>,
+}
+
+impl Proof {
+    /// Returns every rule used by the proof exactly once
+    pub fn rules(&self) -> Vec<Rule> {
+        let mut rules: Vec<Rule> = self.solutions.iter().map(|s| s.rule).collect();
+        rules.sort();
+        rules.dedup();
+        rules
+    }
+}
+
+/// The answering schemes all rules are made of.
+/// Two rules are compatible if their schemes are either identical or use different squares.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Atom {
+    /// White plays the lower square (given), Black the one directly above
+    Pair(u64),
+    /// Two directly playable squares. Black plays the other one
+    Inverse(u64, u64),
+    /// Directly playable squares first, second and third.
+    /// White first -> Black third. White second -> Black third. White third -> Black second.
+    /// Afterwards the remaining squares are answered as a Claimeven or Baseinverse.
+    BaseClaim { first: u64, second: u64, third: u64 },
+    /// The lowest squares of two stacks of three empty squares.
+    /// Black answers in the same column or in the other one, depending on which squares are
+    /// still playable. All squares below the stacks have to be answered by follow-up.
+    HighInverse(u64, u64),
+    /// All empty squares of a column with an odd number of them, starting with the given
+    /// lowest square in an even row. White plays an even square -> Black the odd one above.
+    /// The top square is left over and answered together with another column.
+    Column(u64),
+    /// Two columns answered as Column. White plays the left over top square of one column ->
+    /// Black plays the lowest square of the other one, which is answered by follow-up afterwards.
+    /// Black still gets one square of every Vertical and one of the two top squares.
+    LowInverse(u64, u64),
+}
+
+impl Atom {
+    fn squares(&self) -> u64 {
+        match *self {
+            Atom::Pair(lower) => lower | (lower << 1),
+            Atom::Inverse(a, b) => a | b,
+            Atom::BaseClaim { first, second, third } => first | second | (second << 1) | third,
+            Atom::HighInverse(a, b) => (a | b) * 0b111,
+            Atom::Column(base) => base | above(base),
+            Atom::LowInverse(a, b) => a | above(a) | b | above(b),
+        }
+    }
+
+    /// Squares that may not be used by any other scheme
+    fn reserved(&self) -> u64 {
+        match *self {
+            Atom::HighInverse(a, b) => self.squares() | below(a) | below(b),
+            _ => self.squares(),
+        }
+    }
+
+    fn is_compatible(&self, other: &Atom) -> bool {
+        self == other || self.is_part_of(other) || other.is_part_of(self)
+            || (self.reserved() & other.squares() == 0 && other.reserved() & self.squares() == 0)
+    }
+
+    /// Whether the other scheme answers these squares the same way
+    fn is_part_of(&self, other: &Atom) -> bool {
+        matches!((*self, *other), (Atom::Column(column), Atom::LowInverse(a, b)) if column == a || column == b)
+    }
+}
+
+/// Returns all fields below the given field in the same column
+#[inline]
+fn below(field: u64) -> u64 {
+    let column_start = BitBoard::FIRST_COLUMN << (column_of(field) as usize * COLUMN_BITS);
+    (field - 1) & column_start
+}
+
+/// Returns all fields above the given field in the same column
+#[inline]
+fn above(field: u64) -> u64 {
+    let column_start = BitBoard::FIRST_COLUMN << (column_of(field) as usize * COLUMN_BITS);
+    !(field | (field - 1)) & column_start & BitBoard::PLAYABLE_FIELDS
+}
+
+/// Returns the lower squares of all Verticals in the column with the given lowest empty square
+#[inline]
+fn verticals(base: u64) -> u64 {
+    (base | above(base)) & (BitBoard::BOTTOM_LINE * 0b001010)
+}
+
+/// Whether the field lies in an even row (counted from 1 at the bottom)
+#[inline]
+fn is_even_row(field: u64) -> bool {
+    field.trailing_zeros() as usize % COLUMN_BITS % 2 == 1
+}
+
+#[inline]
+fn column_of(field: u64) -> u32 {
+    field.trailing_zeros() / COLUMN_BITS as u32
+}
+
+/// Iterates over all set bits of a board
+fn fields(mut board: u64) -> impl Iterator<Item = u64> {
+    std::iter::from_fn(move || {
+        if board == 0 {
+            return None;
+        }
+        let field = board & board.wrapping_neg();
+        board ^= field;
+        Some(field)
+    })
+}
+
+/// Returns all 69 possible groups (four fields in a row) of the board
+pub fn all_groups() -> Vec<u64> {
+    let mut groups = Vec::with_capacity(69);
+    // Vertical, horizontal, diagonal up-right, diagonal down-right
+    let directions: [(i32, i32); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+    for column in 0..BOARD_WIDTH as i32 {
+        for row in 0..BOARD_HEIGHT as i32 {
+            for (dx, dy) in directions.iter() {
+                let end_column = column + 3 * dx;
+                let end_row = row + 3 * dy;
+                if end_column >= BOARD_WIDTH as i32 || end_row < 0 || end_row >= BOARD_HEIGHT as i32 {
+                    continue;
+                }
+                let group = (0..4).fold(0, |group, i| group | 1 << ((column + i * dx) * COLUMN_BITS as i32 + row + i * dy));
+                groups.push(group);
+            }
+        }
+    }
+    groups
+}
+
+/// Tries to prove that the second player can at least draw.
+/// The first player has to be on the move and the game must not be over.
+/// Returns the rules used for the proof or None if no proof was found.
+pub fn prove(board: &BitBoard) -> Option<Proof> {
+    if !can_prove(board) {
+        return None;
+    }
+    Prover::new(board).prove()
+}
+
+/// Tries to prove that the second player wins, with the same requirements as prove().
+/// The proof contains the Aftereven that completes a group of the second player, even if it
+/// refutes no group of the first player.
+pub fn prove_win(board: &BitBoard) -> Option<Proof> {
+    if !can_prove(board) {
+        return None;
+    }
+    Prover::new(board).prove_win()
+}
+
+fn can_prove(board: &BitBoard) -> bool {
+    board.is_first_player() && !board.has_won() && !board.has_lost() && board.winning_moves() == 0
+}
+
+struct Prover {
+    groups: Vec<u64>,
+    /// Groups without stones of Black, that have to be refuted
+    white_groups: u128,
+    solutions: Vec<Solution>,
+    /// Afterevens of all Black groups, whether they refute White groups or not
+    afterevens: Vec<Solution>,
+    empty: u64,
+    playable: u64,
+    steps: u32,
+}
+
+impl Prover {
+    fn new(board: &BitBoard) -> Prover {
+        let white = board.get_player_bit_repr();
+        let black = board.get_opponent_bit_repr();
+        let groups = all_groups();
+
+        let white_groups = groups.iter().enumerate()
+            .filter(|(_, group)| *group & black == 0)
+            .fold(0u128, |mask, (i, _)| mask | 1 << i);
+
+        let mut prover = Prover {
+            groups,
+            white_groups,
+            solutions: Vec::new(),
+            afterevens: Vec::new(),
+            empty: BitBoard::PLAYABLE_FIELDS & !(white | black),
+            playable: board.all_possible_moves(),
+            steps: 0,
+        };
+        prover.find_solutions(white);
+        prover
+    }
+
+    /// Returns the White groups that satisfy the given condition
+    fn refuted_by<F: Fn(u64) -> bool>(&self, condition: F) -> u128 {
+        self.groups.iter().enumerate()
+            .filter(|(i, group)| self.white_groups & (1 << i) > 0 && condition(**group))
+            .fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+
+    fn add(&mut self, rule: Rule, atoms: Vec<Atom>, refutes: u128) {
+        if refutes == 0 {
+            return;
+        }
+        let squares = atoms.iter().fold(0, |squares, atom| squares | atom.squares());
+        self.solutions.push(Solution { rule, squares, atoms, refutes });
+    }
+
+    fn find_solutions(&mut self, white: u64) {
+        let empty = self.empty;
+        let playable = self.playable;
+        // Empty fields with an empty field directly above them
+        let lower = empty & (empty >> 1);
+
+        for l in fields(lower) {
+            let u = l << 1;
+            if is_even_row(u) {
+                let refutes = self.refuted_by(|g| g & u > 0);
+                self.add(Rule::Claimeven, vec![Atom::Pair(l)], refutes);
+            }
+        }
+
+        // Vertical: The lowest squares of columns with an odd number of empty squares are even
+        let odd_columns = playable & (BitBoard::BOTTOM_LINE * 0b101010);
+        for base in fields(odd_columns) {
+            for l in fields(verticals(base)) {
+                let refutes = self.refuted_by(|g| g & (l | l << 1) == l | l << 1);
+                self.add(Rule::Vertical, vec![Atom::Column(base)], refutes);
+            }
+        }
+
+        for a in fields(playable) {
+            for b in fields(playable & !(a | (a - 1))) {
+                let refutes = self.refuted_by(|g| g & (a | b) == a | b);
+                self.add(Rule::Baseinverse, vec![Atom::Inverse(a, b)], refutes);
+            }
+        }
+
+        // Lowinverse: Two columns answered with Verticals
+        let top_row = BitBoard::BOTTOM_LINE << (BOARD_HEIGHT - 1);
+        for a in fields(odd_columns) {
+            for b in fields(odd_columns & !(a | (a - 1))) {
+                let tops = (a | above(a) | b | above(b)) & top_row;
+                let lowers = verticals(a) | verticals(b);
+                let refutes = self.refuted_by(|g| {
+                    g & tops == tops || fields(lowers).any(|l| g & (l | l << 1) == l | l << 1)
+                });
+                self.add(Rule::Lowinverse, vec![Atom::LowInverse(a, b)], refutes);
+            }
+        }
+
+        // Highinverse: Three empty fields in two columns each, the upper ones even
+        let stacks = lower & (lower >> 1) & (BitBoard::BOTTOM_LINE * 0b001010);
+        for a in fields(stacks) {
+            for b in fields(stacks & !(a | (a - 1))) {
+                if column_of(a) == column_of(b) {
+                    continue;
+                }
+                let (a_mid, a_top, b_mid, b_top) = (a << 1, a << 2, b << 1, b << 2);
+                let a_playable = a & playable > 0;
+                let b_playable = b & playable > 0;
+                let refutes = self.refuted_by(|g| {
+                    let contains = |fields: u64| g & fields == fields;
+                    contains(a_top | b_top) || contains(a_mid | b_mid)
+                        || contains(a_mid | a_top) || contains(b_mid | b_top)
+                        || (a_playable && contains(a | b_top))
+                        || (b_playable && contains(b | a_top))
+                });
+                self.add(Rule::Highinverse, vec![Atom::HighInverse(a, b)], refutes);
+            }
+        }
+
+        // Baseclaim: Three directly playable fields, the one above the second field is even
+        for second in fields(playable & (empty >> 1) & (BitBoard::BOTTOM_LINE * 0b010101)) {
+            for first in fields(playable & !second) {
+                for third in fields(playable & !(second | first)) {
+                    let refutes = self.refuted_by(|g| {
+                        g & (first | second << 1) == first | second << 1 || g & (second | third) == second | third
+                    });
+                    self.add(Rule::Baseclaim, vec![Atom::BaseClaim { first, second, third }], refutes);
+                }
+            }
+        }
+
+        let black_groups: Vec<u64> = self.groups.iter().copied().filter(|g| g & white == 0).collect();
+
+        for group in black_groups {
+            let group_empty = group & empty;
+            if group_empty == 0 {
+                continue;
+            }
+
+            // Aftereven: Black can complete the group using Claimevens only
+            let aftereven = fields(group_empty).all(|e| is_even_row(e) && (e >> 1) & empty > 0);
+            if aftereven {
+                let atoms: Vec<Atom> = fields(group_empty).map(|e| Atom::Pair(e >> 1)).collect();
+                let columns_above: Vec<u64> = fields(group_empty).map(above).collect();
+                let refutes = self.refuted_by(|g| columns_above.iter().all(|above| g & above > 0));
+                let squares = atoms.iter().fold(0, |squares, atom| squares | atom.squares());
+                self.afterevens.push(Solution { rule: Rule::Aftereven, squares, atoms: atoms.clone(), refutes });
+                self.add(Rule::Aftereven, atoms, refutes);
+            }
+
+            // Before and Specialbefore need at most one empty field per column and room above them
+            let is_vertical = group & (group >> 1) > 0;
+            if is_vertical || group_empty & top_row > 0 {
+                continue;
+            }
+            let successors = group_empty << 1;
+
+            let refutes = self.refuted_by(|g| g & successors == successors);
+            for atoms in self.before_atoms(group_empty) {
+                self.add(Rule::Before, atoms, refutes);
+            }
+
+            for base in fields(group_empty & playable) {
+                let columns = fields(group_empty).fold(0, |columns, e| columns | BitBoard::FIRST_COLUMN << (column_of(e) as usize * COLUMN_BITS));
+                for extra in fields(playable & !columns) {
+                    let refutes = self.refuted_by(|g| g & (successors | extra) == successors | extra);
+                    for mut atoms in self.before_atoms(group_empty & !base) {
+                        atoms.push(Atom::Inverse(base.min(extra), base.max(extra)));
+                        self.add(Rule::Specialbefore, atoms, refutes);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns all ways for Black to get either each of the given squares or the square directly
+    /// above it: A Claimeven above odd squares, and a Claimeven below or a Vertical for even ones
+    fn before_atoms(&self, squares: u64) -> Vec<Vec<Atom>> {
+        let mut combinations = vec![Vec::new()];
+        for e in fields(squares) {
+            let mut options = Vec::new();
+            if !is_even_row(e) {
+                options.push(Atom::Pair(e));
+            } else {
+                if (e >> 1) & self.empty > 0 {
+                    options.push(Atom::Pair(e >> 1));
+                }
+                let base = (e | below(e) & self.empty) & self.playable;
+                if is_even_row(base) {
+                    options.push(Atom::Column(base));
+                }
+            }
+            combinations = combinations.iter()
+                .flat_map(|atoms| options.iter().map(move |option| [atoms.as_slice(), &[*option]].concat()))
+                .collect();
+        }
+        combinations
+    }
+
+    fn is_compatible(&self, solution: &Solution, chosen: &[usize]) -> bool {
+        chosen.iter().all(|c| {
+            self.solutions[*c].atoms.iter()
+                .all(|a| solution.atoms.iter().all(|b| a.is_compatible(b)))
+        })
+    }
+
+    fn prove(mut self) -> Option<Proof> {
+        let mut chosen = Vec::new();
+        let fill = self.search(&mut chosen, 0)?;
+        Some(self.proof(&chosen, fill))
+    }
+
+    /// Searches a proof that starts with the Aftereven of a Black group
+    fn prove_win(mut self) -> Option<Proof> {
+        for aftereven in std::mem::take(&mut self.afterevens) {
+            let refutes = aftereven.refutes;
+            self.solutions.push(aftereven);
+            let mut chosen = vec![self.solutions.len() - 1];
+            self.steps = 0;
+            if let Some(fill) = self.search(&mut chosen, refutes) {
+                return Some(self.proof(&chosen, fill));
+            }
+            self.solutions.pop();
+        }
+        None
+    }
+
+    fn proof(&self, chosen: &[usize], fill: Vec<Atom>) -> Proof {
+        let mut solutions: Vec<Solution> = chosen.iter().map(|c| self.solutions[*c].clone()).collect();
+        // Identical solutions may be needed for several groups
+        solutions.dedup();
+        for atom in fill {
+            let rule = match atom {
+                Atom::LowInverse(_, _) => Rule::Lowinverse,
+                _ => Rule::Baseinverse,
+            };
+            solutions.push(Solution { rule, squares: atom.squares(), atoms: vec![atom], refutes: 0 });
+        }
+        Proof { solutions }
+    }
+
+    /// Searches a set of compatible solutions refuting all White groups.
+    /// Returns the Baseinverses and Lowinverses needed to answer every move of White.
+    fn search(&mut self, chosen: &mut Vec<usize>, refuted: u128) -> Option<Vec<Atom>> {
+        self.steps += 1;
+        if self.steps > MAX_SEARCH_STEPS {
+            return None;
+        }
+
+        let open = self.white_groups & !refuted;
+        if open == 0 {
+            return self.fill_columns(chosen);
+        }
+
+        // Continue with the group that has the fewest candidates
+        let mut best: Option<Vec<usize>> = None;
+        for group in 0..self.groups.len() {
+            if open & (1 << group) == 0 {
+                continue;
+            }
+            let candidates: Vec<usize> = (0..self.solutions.len())
+                .filter(|s| self.solutions[*s].refutes & (1 << group) > 0)
+                .filter(|s| self.is_compatible(&self.solutions[*s], chosen))
+                .collect();
+            if candidates.is_empty() {
+                return None;
+            }
+            if best.as_ref().is_none_or(|b| candidates.len() < b.len()) {
+                best = Some(candidates);
+            }
+        }
+
+        for candidate in best.unwrap_or_default() {
+            chosen.push(candidate);
+            let result = self.search(chosen, refuted | self.solutions[candidate].refutes);
+            if result.is_some() {
+                return result;
+            }
+            chosen.pop();
+        }
+        None
+    }
+
+    /// Checks that every square not used by the chosen rules can be answered by a follow-up.
+    /// Columns with an odd number of remaining squares are paired with Baseinverses on their
+    /// lowest square, columns answered with Verticals with Lowinverses. Returns these pairs.
+    fn fill_columns(&self, chosen: &[usize]) -> Option<Vec<Atom>> {
+        let atoms: Vec<Atom> = chosen.iter().flat_map(|c| self.solutions[*c].atoms.iter().copied()).collect();
+        let used = atoms.iter().fold(0, |used, atom| used | atom.squares());
+        let reserved = atoms.iter().fold(0, |reserved, atom| reserved | atom.reserved());
+
+        let mut odd_columns = Vec::new();
+        for column in 0..BOARD_WIDTH {
+            let mut run = 0;
+            let mut odd_run_at_base = false;
+            for row in 0..=BOARD_HEIGHT {
+                let field: u64 = 1 << (column * COLUMN_BITS + row);
+                if row < BOARD_HEIGHT && field & self.empty & !used > 0 {
+                    run += 1;
+                    continue;
+                }
+                if run % 2 == 1 {
+                    // Only the lowest run can be fixed by playing its lowest square
+                    let base = field >> run;
+                    if odd_run_at_base || base & self.playable == 0 || base & reserved > 0 {
+                        return None;
+                    }
+                    odd_run_at_base = true;
+                }
+                run = 0;
+            }
+            if odd_run_at_base {
+                odd_columns.push(BitBoard::FIRST_COLUMN << (column * COLUMN_BITS) & self.playable);
+            }
+        }
+
+        let paired = atoms.iter().fold(0, |paired, atom| match atom {
+            Atom::LowInverse(a, b) => paired | a | b,
+            _ => paired,
+        });
+        let vertical_columns: Vec<u64> = fields(atoms.iter().fold(0, |columns, atom| match atom {
+            Atom::Column(base) => columns | base,
+            _ => columns,
+        }) & !paired).collect();
+
+        if odd_columns.len() % 2 == 1 || vertical_columns.len() % 2 == 1 {
+            return None;
+        }
+        let inverses = odd_columns.chunks(2).map(|pair| Atom::Inverse(pair[0], pair[1]));
+        let low_inverses = vertical_columns.chunks(2).map(|pair| Atom::LowInverse(pair[0], pair[1]));
+        Some(inverses.chain(low_inverses).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::BitBoard;
+    use crate::engine::{solve, SolverType};
+    use crate::knowledge::{all_groups, prove, prove_win, Rule};
+    use crate::positions::PositionGenerator;
+
+    /// Score of the first player under perfect play
+    fn score(board: &BitBoard) -> i32 {
+        solve(board, 42, SolverType::Weak).unwrap().score
+    }
+
+    #[test]
+    fn test_all_groups() {
+        let groups = all_groups();
+        assert_eq!(groups.len(), 69);
+        assert!(groups.iter().all(|g| g.count_ones() == 4 && g & !BitBoard::PLAYABLE_FIELDS == 0));
+    }
+
+    #[test]
+    fn test_prove_claimeven() {
+        // Black answers every move of White directly on top
+        let board =
+            "npnpnnn
+            ncncnnn
+            ncncccn
+            npncppn
+            cccpppn
+            ppcpcpn";
+        let board = BitBoard::from_string(board).unwrap();
+
+        let proof = prove(&board).unwrap();
+        assert_eq!(proof.rules(), vec![Rule::Claimeven]);
+        // Rules only use empty squares
+        let empty = !board.get_player_bit_repr() & !board.get_opponent_bit_repr() & BitBoard::PLAYABLE_FIELDS;
+        assert!(proof.solutions.iter().all(|s| s.squares & !empty == 0));
+    }
+
+    #[test]
+    fn test_prove_highinverse() {
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            pnncnnp
+            cnnppcc
+            ppncccp
+            cpnpccp";
+        let board = BitBoard::from_string(board).unwrap();
+
+        let proof = prove(&board).unwrap();
+        assert_eq!(proof.rules(), vec![Rule::Claimeven, Rule::Aftereven, Rule::Highinverse]);
+    }
+
+    #[test]
+    fn test_prove_rules() {
+        // Each proof needs the last of its rules, the search confirms that White can not win
+        let positions = [
+            ("nnnnnpn
+            cnnpcpc
+            cnncpcp
+            pnncppc
+            cpppccc
+            ppcppcc", vec![Rule::Claimeven, Rule::Baseinverse]),
+            ("pnncnpn
+            cnncnpn
+            pcppncn
+            cpccppn
+            cpcppcn
+            cppccpc", vec![Rule::Claimeven, Rule::Vertical, Rule::Lowinverse]),
+            ("ppncnnn
+            ccnpnnn
+            cpccnnc
+            ppccnnp
+            cpcppcc
+            pcppcpp", vec![Rule::Claimeven, Rule::Aftereven]),
+            ("nnpnnpn
+            cncnnpc
+            cppnncc
+            pccnnpc
+            pcppccp
+            ccpppcp", vec![Rule::Claimeven, Rule::Baseclaim]),
+            ("pnnncnn
+            cpnnpnn
+            ccpnccc
+            cpcncpp
+            pcpnpcp
+            pcpppcc", vec![Rule::Lowinverse, Rule::Before]),
+            ("ncnnpcn
+            ncnncpn
+            cpnncpn
+            pcccpcn
+            pppccpp
+            ppcppcc", vec![Rule::Claimeven, Rule::Specialbefore]),
+        ];
+        for (board, rules) in positions {
+            let board = BitBoard::from_string(board).unwrap();
+            assert_eq!(prove(&board).unwrap().rules(), rules);
+            assert!(score(&board) <= 0);
+        }
+    }
+
+    #[test]
+    fn test_prove_win() {
+        // Black completes one of his groups with Claimevens
+        let board =
+            "pncnnnn
+            cnpnnnp
+            pcccnnc
+            pcpcnpp
+            ccpcppc
+            cpppccp";
+        let board = BitBoard::from_string(board).unwrap();
+        let proof = prove_win(&board).unwrap();
+        assert_eq!(proof.rules(), vec![Rule::Aftereven, Rule::Lowinverse]);
+        assert!(score(&board) < 0);
+
+        // Only a draw
+        let board =
+            "pnncnpn
+            cnncnpn
+            pcppncn
+            cpccppn
+            cpcppcn
+            cppccpc";
+        let board = BitBoard::from_string(board).unwrap();
+        assert_eq!(score(&board), 0);
+        assert_eq!(prove_win(&board), None);
+    }
+
+    #[test]
+    fn test_proofs_agree_with_search() {
+        for board in PositionGenerator::new(1, 30).take(200) {
+            if prove(&board).is_some() {
+                assert!(score(&board) <= 0, "{}", board);
+            }
+            if prove_win(&board).is_some() {
+                assert!(score(&board) < 0, "{}", board);
+            }
+        }
+    }
+
+    #[test]
+    fn test_prove_first_player_win() {
+        // The first player wins on the empty board
+        assert_eq!(prove(&BitBoard::empty()), None);
+    }
+
+    #[test]
+    fn test_prove_requires_white_to_move() {
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnncnnn";
+        let board = BitBoard::from_string(board).unwrap();
+        assert_eq!(prove(&board), None);
+    }
+}
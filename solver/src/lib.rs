@@ -1,6 +1,11 @@
 mod utils;
+pub mod bits;
 pub mod board;
+pub mod endgame;
 pub mod engine;
-mod transposition;
-
-use wasm_bindgen::prelude::*;
\ No newline at end of file
+pub mod game;
+pub mod knowledge;
+pub mod perft;
+pub mod popout;
+pub mod positions;
+pub mod transposition;
@@ -0,0 +1,93 @@
+//! Counting of move sequences and positions to validate the move generation
+//!
+//! perft() walks the whole game tree up to a depth, perft_unique() counts the distinct
+//! positions at that depth instead. Both never continue a decided game, so they check
+//! all_possible_moves(), play_field() and the win detection together.
+
+use crate::bits::Bits;
+use crate::board::Variant;
+use std::collections::HashSet;
+
+/// Number of distinct positions after each ply on the standard board (OEIS A212693)
+pub const POSITIONS_PER_PLY: [u64; 15] = [
+    1, 7, 49, 238, 1120, 4263, 16422, 54859, 184275, 558186, 1662623, 4568683, 12236101, 30929111, 75437595,
+];
+
+/// Number of move sequences of the given length from the board. No move is played after a
+/// player has won, so sequences through decided positions are not counted
+pub fn perft<V: Variant>(board: &V, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if is_decided(board) {
+        return 0;
+    }
+    let moves = board.all_possible_moves();
+    if depth == 1 {
+        return moves.count_ones() as u64;
+    }
+    (0..V::WIDTH as u64)
+        .map(|column| V::move_in_row(moves, column))
+        .filter(|mov| *mov != V::Bits::ZERO)
+        .map(|mov| perft(&board.play_field(mov), depth - 1))
+        .sum()
+}
+
+/// Number of distinct positions reachable from the board with exactly depth moves, with the
+/// same rules as perft()
+pub fn perft_unique<V: Variant>(board: &V, depth: u8) -> u64 {
+    let mut positions = vec![*board];
+    for _ in 0..depth {
+        let mut keys = HashSet::new();
+        let mut next = Vec::new();
+        for board in positions.iter().filter(|board| !is_decided(*board)) {
+            let moves = board.all_possible_moves();
+            for column in 0..V::WIDTH as u64 {
+                let mov = V::move_in_row(moves, column);
+                if mov == V::Bits::ZERO {
+                    continue;
+                }
+                let new_board = board.play_field(mov);
+                if keys.insert(new_board.key()) {
+                    next.push(new_board);
+                }
+            }
+        }
+        positions = next;
+    }
+    positions.len() as u64
+}
+
+/// Whether a player has won the game. Full boards have no moves left anyway
+fn is_decided<V: Variant>(board: &V) -> bool {
+    board.has_won() || board.has_lost()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{BitBoard, Board, FieldType};
+    use crate::perft::{perft, perft_unique, POSITIONS_PER_PLY};
+
+    #[test]
+    fn test_perft() {
+        let empty = BitBoard::empty();
+        let counts: Vec<u64> = (0..6).map(|depth| perft(&empty, depth)).collect();
+        assert_eq!(counts, vec![1, 7, 49, 343, 2401, 16807]);
+        for depth in 0..7 {
+            assert_eq!(perft_unique(&empty, depth), POSITIONS_PER_PLY[depth as usize]);
+        }
+
+        // The first player wins with the next move in the first column
+        let board = BitBoard::from_moves("121212").unwrap();
+        assert_eq!(perft(&board, 1), 7);
+        assert_eq!(perft(&board, 2), 6 * 7);
+        assert_eq!(perft_unique(&board, 2), 6 * 7);
+        assert_eq!(perft(&board.play_column(0, FieldType::Player).unwrap(), 1), 0);
+
+        // A full column leaves fewer moves
+        assert_eq!(perft(&BitBoard::from_moves("444444").unwrap(), 1), 6);
+        // Completing the line also ends misère games
+        let board = Board::<7, 6, u64, 4, false, true>::from_moves("121212").unwrap();
+        assert_eq!(perft(&board, 2), 6 * 7);
+    }
+}
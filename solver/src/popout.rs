@@ -0,0 +1,346 @@
+//! The PopOut variant
+//!
+//! Instead of dropping a stone, a player may remove (pop) one of his own stones from the
+//! bottom row. All stones above it fall down one row. Compared to connect four:
+//! - If a pop completes four in a row for both players, the player who popped wins
+//! - A full board does not end the game, as long as the player to move can pop
+//! - The game is a draw as soon as the same position occurs for the third time
+
+use crate::board::{BitBoard, BoardError, JsBoard, BOARD_WIDTH};
+use crate::game::{Outcome, Player};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Number of occurrences of the same position that end the game in a draw
+pub const REPETITIONS: u8 = 3;
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MoveKind {
+    /// Drops a stone into the column
+    Drop,
+    /// Removes the own stone at the bottom of the column
+    Pop,
+}
+
+/// A move of PopOut
+#[wasm_bindgen]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PopOutMove {
+    pub kind: MoveKind,
+    /// Column starting at 0 on the left
+    pub column: u8,
+}
+
+#[wasm_bindgen]
+impl PopOutMove {
+    pub fn drop(column: u8) -> PopOutMove {
+        PopOutMove { kind: MoveKind::Drop, column }
+    }
+
+    pub fn pop(column: u8) -> PopOutMove {
+        PopOutMove { kind: MoveKind::Pop, column }
+    }
+}
+
+impl PopOutMove {
+    /// Plays the move for the current player and changes the player.
+    /// Fails if the move is not possible in the position
+    pub fn play(&self, board: &BitBoard) -> Result<BitBoard, BoardError> {
+        let column = self.column;
+        if column as usize >= BOARD_WIDTH {
+            return Err(BoardError::ColumnOutOfRange { column });
+        }
+        match self.kind {
+            MoveKind::Drop => {
+                let field = BitBoard::move_in_row(board.all_possible_moves(), column as u64);
+                if field == 0 {
+                    return Err(BoardError::ColumnFull { column });
+                }
+                Ok(board.play_field(field))
+            }
+            MoveKind::Pop => {
+                let field = BitBoard::move_in_row(board.possible_pops(), column as u64);
+                if field == 0 {
+                    return Err(BoardError::NoStoneToPop { column });
+                }
+                Ok(board.pop_field(field))
+            }
+        }
+    }
+
+    /// All moves of the current player, the drops before the pops
+    pub fn all_possible(board: &BitBoard) -> Vec<PopOutMove> {
+        let drops = (0..BOARD_WIDTH as u8).map(PopOutMove::drop);
+        let pops = (0..BOARD_WIDTH as u8).map(PopOutMove::pop);
+        drops.chain(pops).filter(|mov| mov.play(board).is_ok()).collect()
+    }
+}
+
+/// Whether the last move decided the game. Returns 1 if the player to move won, -1 if he lost.
+/// The player who made the move wins, if it completed four in a row for both players
+pub fn decided(board: &BitBoard) -> Option<i32> {
+    if board.has_lost() {
+        Some(-1)
+    } else if board.has_won() {
+        // Only possible after a pop of the opponent
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Key of the position for the repetition rule. The same stones are a different position,
+/// if the other player has to move
+pub fn position_key(board: &BitBoard, first_to_move: bool) -> u64 {
+    board.key() | (first_to_move as u64) << 63
+}
+
+/// A game of PopOut with the occurrences of all positions for the repetition rule
+#[wasm_bindgen]
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PopOut {
+    board: BitBoard,
+    moves: Vec<PopOutMove>,
+    /// Number of occurrences of every position of the game, see position_key()
+    positions: HashMap<u64, u8>,
+}
+
+impl Default for PopOut {
+    fn default() -> Self {
+        PopOut::from_board(BitBoard::empty())
+    }
+}
+
+#[wasm_bindgen]
+impl PopOut {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PopOut {
+        PopOut::default()
+    }
+
+    /// The current position
+    #[wasm_bindgen(js_name = board)]
+    pub fn js_board(&self) -> JsBoard {
+        self.board.into()
+    }
+
+    /// All played moves
+    pub fn moves(&self) -> Vec<PopOutMove> {
+        self.moves.clone()
+    }
+
+    /// The player to move. Every drop adds a stone and every pop removes one,
+    /// so the first player is to move whenever the number of stones is even
+    pub fn turn(&self) -> Player {
+        if self.board.number_of_stones().is_multiple_of(2) {
+            Player::First
+        } else {
+            Player::Second
+        }
+    }
+
+    /// Returns None while the game is not over
+    pub fn outcome(&self) -> Option<Outcome> {
+        let winner = match (decided(&self.board), self.turn()) {
+            (None, _) => None,
+            (Some(1), player) => Some(player),
+            (Some(_), Player::First) => Some(Player::Second),
+            (Some(_), Player::Second) => Some(Player::First),
+        };
+        match winner {
+            Some(Player::First) => Some(Outcome::FirstPlayerWins),
+            Some(Player::Second) => Some(Outcome::SecondPlayerWins),
+            None if self.repetitions() >= REPETITIONS => Some(Outcome::Draw),
+            // A full board without an own stone at the bottom
+            None if PopOutMove::all_possible(&self.board).is_empty() => Some(Outcome::Draw),
+            None => None,
+        }
+    }
+
+    /// How often the current position occurred in the game
+    pub fn repetitions(&self) -> u8 {
+        self.positions[&self.key()]
+    }
+
+    /// Plays the move for the player to move
+    pub fn play(&mut self, mov: PopOutMove) -> Result<(), BoardError> {
+        if self.outcome().is_some() {
+            return Err(BoardError::GameOver);
+        }
+        self.board = mov.play(&self.board)?;
+        self.moves.push(mov);
+        *self.positions.entry(self.key()).or_insert(0) += 1;
+        Ok(())
+    }
+}
+
+impl PopOut {
+    /// Starts the game from the position. The player to move follows from the number of stones
+    pub fn from_board(board: BitBoard) -> PopOut {
+        let mut game = PopOut {
+            board,
+            moves: Vec::new(),
+            positions: HashMap::new(),
+        };
+        game.positions.insert(game.key(), 1);
+        game
+    }
+
+    /// The current position
+    pub fn board(&self) -> BitBoard {
+        self.board
+    }
+
+    /// Number of occurrences of every position of the game, see position_key()
+    pub fn positions(&self) -> &HashMap<u64, u8> {
+        &self.positions
+    }
+
+    /// Key of the current position
+    fn key(&self) -> u64 {
+        position_key(&self.board, self.turn() == Player::First)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{BitBoard, BoardError};
+    use crate::game::{Outcome, Player};
+    use crate::popout::{decided, position_key, MoveKind, PopOut, PopOutMove};
+
+    #[test]
+    fn test_pop() {
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnncnnn
+            nnnpnnn
+            nnnpcnc";
+        let board = BitBoard::from_string(board).unwrap();
+        assert_eq!(board.possible_pops().count_ones(), 1);
+
+        let popped = PopOutMove::pop(3).play(&board).unwrap();
+        let expected =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnpnnn
+            nnncpnp";
+        // Popping removed a stone of the player who moved
+        assert_eq!(popped, BitBoard::from_string_unchecked(expected).unwrap());
+
+        assert_eq!(PopOutMove::pop(4).play(&board), Err(BoardError::NoStoneToPop { column: 4 }));
+        assert_eq!(PopOutMove::pop(0).play(&board), Err(BoardError::NoStoneToPop { column: 0 }));
+        assert_eq!(PopOutMove::drop(7).play(&board), Err(BoardError::ColumnOutOfRange { column: 7 }));
+        let moves = PopOutMove::all_possible(&board);
+        assert_eq!(moves.len(), 8);
+        assert_eq!(moves.iter().filter(|mov| mov.kind == MoveKind::Pop).count(), 1);
+    }
+
+    #[test]
+    fn test_simultaneous_four() {
+        // Popping the first column completes four for both players
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            pnnnnnn
+            cpppnnn
+            pcccnnc";
+        let board = BitBoard::from_string(board).unwrap();
+        let popped = PopOutMove::pop(0).play(&board).unwrap();
+        assert!(popped.has_won() && popped.has_lost());
+        assert_eq!(decided(&popped), Some(-1));
+
+        let mut game = PopOut::from_board(board);
+        game.play(PopOutMove::pop(0)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::FirstPlayerWins));
+        assert_eq!(game.play(PopOutMove::drop(5)), Err(BoardError::GameOver));
+
+        // Popping the stone below the opponent's stone completes only his four
+        let board =
+            "nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            nnnnnnn
+            cpppnnn
+            pcccnnn";
+        let board = BitBoard::from_string(board).unwrap();
+        assert_eq!(decided(&PopOutMove::pop(0).play(&board).unwrap()), Some(1));
+        let mut game = PopOut::from_board(board);
+        game.play(PopOutMove::pop(0)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::SecondPlayerWins));
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut game = PopOut::new();
+        assert_eq!(game.repetitions(), 1);
+        for _ in 0..2 {
+            assert_eq!(game.outcome(), None);
+            for mov in [PopOutMove::drop(0), PopOutMove::drop(1), PopOutMove::pop(0), PopOutMove::pop(1)] {
+                game.play(mov).unwrap();
+            }
+        }
+        assert_eq!(game.board(), BitBoard::empty());
+        assert_eq!(game.turn(), Player::First);
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+        assert_eq!(game.moves().len(), 8);
+
+        // The same stones with the other player to move are a different position
+        let board = BitBoard::from_moves("4").unwrap();
+        assert_ne!(position_key(&board, true), position_key(&board, false));
+    }
+
+    #[test]
+    fn test_second_player_to_move() {
+        // Seven stones, so the second player is to move and completes four in the second column
+        let board = BitBoard::from_moves("1212123").unwrap();
+        let mut game = PopOut::from_board(board);
+        assert_eq!(game.turn(), Player::Second);
+        assert_eq!(game.positions().get(&position_key(&board, false)), Some(&1));
+        game.play(PopOutMove::drop(1)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::SecondPlayerWins));
+    }
+
+    #[test]
+    fn test_full_board() {
+        let mut game = PopOut::new();
+        // Fills the board column pair by column pair without any four, like in connect four
+        for columns in [[0, 1], [2, 3], [4, 5]] {
+            for i in 0..6 {
+                let offset = if i < 3 { 0 } else { 1 };
+                game.play(PopOutMove::drop(columns[offset])).unwrap();
+                game.play(PopOutMove::drop(columns[1 - offset])).unwrap();
+            }
+        }
+        for _ in 0..6 {
+            game.play(PopOutMove::drop(6)).unwrap();
+        }
+        assert_eq!(game.board().number_of_stones(), 42);
+
+        // The first player can still pop
+        assert_eq!(game.outcome(), None);
+        let moves = PopOutMove::all_possible(&game.board());
+        assert!(!moves.is_empty() && moves.iter().all(|mov| mov.kind == MoveKind::Pop));
+        assert_eq!(game.play(PopOutMove::drop(3)), Err(BoardError::ColumnFull { column: 3 }));
+        game.play(moves[0]).unwrap();
+        assert_eq!(game.board().number_of_stones(), 41);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let json = serde_json::to_string(&PopOutMove::pop(3)).unwrap();
+        assert_eq!(json, r#"{"kind":"Pop","column":3}"#);
+        assert_eq!(serde_json::from_str::<PopOutMove>(&json).unwrap(), PopOutMove::pop(3));
+    }
+}
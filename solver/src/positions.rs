@@ -0,0 +1,146 @@
+//! Seeded generator of random positions for tests and benchmarks
+//!
+//! Positions are rejection sampled: Every move is a uniformly random column. Sequences with
+//! a move into a full column, a move after the game was decided or a terminal final position
+//! are dropped and a new sequence is drawn. Therefore all legal move sequences of the ply that
+//! end in a non-terminal position are equally likely.
+//!
+//! The same seed always produces the same positions.
+
+use crate::board::{BitBoard, BOARD_HEIGHT, BOARD_WIDTH};
+use crate::engine::{solve, SolverType};
+use crate::game::Outcome;
+use rand::prelude::*;
+
+/// Number of sequences drawn for one position before the generator gives up
+pub const MAX_ATTEMPTS: usize = 100_000;
+
+/// Restricts the generated positions
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Filter {
+    /// Every non-terminal position
+    Any,
+    /// Positions with this outcome under perfect play of both players.
+    /// Every candidate is solved completely, which is only fast for late positions
+    Outcome(Outcome),
+    /// Positions where the player to move has exactly this number of moves that block an
+    /// immediate win of the opponent
+    ForcedMoves(u32),
+}
+
+/// Iterator over random legal, non-terminal positions with a fixed number of stones
+#[derive(Clone, Debug)]
+pub struct PositionGenerator {
+    rng: StdRng,
+    ply: u8,
+    filter: Filter,
+}
+
+impl PositionGenerator {
+    /// Generates positions after ply moves. Ply has to be less than 42, as the full board is
+    /// terminal
+    pub fn new(seed: u64, ply: u8) -> PositionGenerator {
+        PositionGenerator {
+            rng: StdRng::seed_from_u64(seed),
+            ply,
+            filter: Filter::Any,
+        }
+    }
+
+    pub fn with_filter(self, filter: Filter) -> PositionGenerator {
+        PositionGenerator { filter, ..self }
+    }
+
+    /// Draws move sequences until one ends in a position passing the filter.
+    /// Returns None after MAX_ATTEMPTS sequences without a position
+    pub fn next_position(&mut self) -> Option<BitBoard> {
+        (0..MAX_ATTEMPTS).find_map(|_| self.random_position().filter(|board| self.accepts(board)))
+    }
+
+    /// Plays a random move sequence. None if it is not legal or ends in a terminal position
+    fn random_position(&mut self) -> Option<BitBoard> {
+        let mut board = BitBoard::empty();
+        for _ in 0..self.ply {
+            if board.has_lost() {
+                return None;
+            }
+            let column = self.rng.gen_range(0..BOARD_WIDTH as u64);
+            let mov = BitBoard::move_in_row(board.all_possible_moves(), column);
+            if mov == 0 {
+                return None;
+            }
+            board = board.play_field(mov);
+        }
+        let full = board.number_of_stones() as usize == BOARD_WIDTH * BOARD_HEIGHT;
+        (!board.has_lost() && !full).then_some(board)
+    }
+
+    fn accepts(&self, board: &BitBoard) -> bool {
+        match self.filter {
+            Filter::Any => true,
+            Filter::Outcome(outcome) => outcome_of(board) == outcome,
+            Filter::ForcedMoves(count) => board.forced_moves().count_ones() == count,
+        }
+    }
+}
+
+impl Iterator for PositionGenerator {
+    type Item = BitBoard;
+
+    fn next(&mut self) -> Option<BitBoard> {
+        self.next_position()
+    }
+}
+
+/// Solves the non-terminal board completely
+pub fn outcome_of(board: &BitBoard) -> Outcome {
+    let score = solve(board, 42, SolverType::Weak).expect("The board is not terminal").score;
+    let first_to_move = board.number_of_stones().is_multiple_of(2);
+    match (score.signum(), first_to_move) {
+        (0, _) => Outcome::Draw,
+        (1, true) | (-1, false) => Outcome::FirstPlayerWins,
+        _ => Outcome::SecondPlayerWins,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::BitBoard;
+    use crate::game::Outcome;
+    use crate::positions::{outcome_of, Filter, PositionGenerator};
+
+    #[test]
+    fn test_generate() {
+        let positions: Vec<BitBoard> = PositionGenerator::new(1, 12).take(50).collect();
+        assert_eq!(positions.len(), 50);
+        for board in &positions {
+            assert_eq!(board.number_of_stones(), 12);
+            assert_eq!(board.validate(), vec![]);
+            assert!(!board.has_lost() && board.all_possible_moves() != 0);
+        }
+        // The same seed generates the same positions
+        assert_eq!(PositionGenerator::new(1, 12).take(50).collect::<Vec<BitBoard>>(), positions);
+        assert_ne!(PositionGenerator::new(2, 12).take(50).collect::<Vec<BitBoard>>(), positions);
+
+        assert_eq!(PositionGenerator::new(1, 0).next(), Some(BitBoard::empty()));
+        // The full board is always terminal
+        assert_eq!(PositionGenerator::new(1, 42).next(), None);
+    }
+
+    #[test]
+    fn test_filter() {
+        for count in 0..=2 {
+            let generator = PositionGenerator::new(3, 20).with_filter(Filter::ForcedMoves(count));
+            let positions: Vec<BitBoard> = generator.take(10).collect();
+            assert_eq!(positions.len(), 10);
+            assert!(positions.iter().all(|board| board.forced_moves().count_ones() == count));
+        }
+
+        for outcome in [Outcome::FirstPlayerWins, Outcome::SecondPlayerWins, Outcome::Draw] {
+            let generator = PositionGenerator::new(4, 32).with_filter(Filter::Outcome(outcome));
+            let positions: Vec<BitBoard> = generator.take(3).collect();
+            assert_eq!(positions.len(), 3);
+            assert!(positions.iter().all(|board| outcome_of(board) == outcome));
+        }
+    }
+}
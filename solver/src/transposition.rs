@@ -2,18 +2,30 @@
 //!
 
 use rand::prelude::*;
-use std::collections::VecDeque;
 
-use crate::board::{BitBoard, FieldType};
+use crate::board::{BitBoard, Move};
 
 /// Implementation of the ['Zobrist'] hash function
 ///
+/// The hash is the xor of one random value per stone, chosen by its field and whether the
+/// first or the second player played it. The player to move follows from the number of
+/// stones, so equal positions have equal hashes, no matter in which order the moves
+/// were played.
+///
 /// ['Zobrist']: https://www.chessprogramming.org/Zobrist_Hashing
 pub struct ZobristHasher {
+    /// Values of the stones of the first player, indexed by BoardField::get_index()
     table_p1: [u64; 42],
+    /// Values of the stones of the second player
     table_p2: [u64; 42],
 }
 
+impl Default for ZobristHasher {
+    fn default() -> Self {
+        ZobristHasher::new()
+    }
+}
+
 impl ZobristHasher {
     pub fn new() -> ZobristHasher {
         let mut table_p1 = [0; 42];
@@ -21,46 +33,52 @@ impl ZobristHasher {
 
         let mut rnd = StdRng::seed_from_u64(42);
 
-        for i in 0..42 {
-            table_p1[i] = rnd.next_u64();
-            table_p2[i] = rnd.next_u64();
+        for (p1, p2) in table_p1.iter_mut().zip(table_p2.iter_mut()) {
+            *p1 = rnd.next_u64();
+            *p2 = rnd.next_u64();
         }
 
         ZobristHasher { table_p1, table_p2 }
     }
 
-    pub fn hash_board(&self, board: &BitBoard) {
+    /// Computes the hash of the position from scratch. The empty board has the hash 0
+    pub fn hash_board(&self, board: &BitBoard) -> u64 {
         let mut hash = 0;
 
+        // The player to move is the first player after an even number of moves
+        let first_to_move = board.number_of_stones().is_multiple_of(2);
         for field in board.field_iter() {
-            if board.is_occupied_by_player_at_field(&field) {
-                hash ^= self.table_p1[field.get_index() as usize];
-            } else if board.is_occupied_at_field(&field) {
-                // Occupied by player2
-                hash ^= self.table_p2[field.get_index() as usize];
+            if !board.is_occupied_at_field(&field) {
+                continue;
             }
+            let first = board.is_occupied_by_player_at_field(&field) == first_to_move;
+            hash ^= self.field_value(field.get_index(), first);
         }
+        hash
+    }
+
+    /// Returns the hash of board.play_field(mov), given the hash of the board before the move.
+    /// Xors the hash with the random value of the field and the player to move, so the same
+    /// call takes the move back again
+    #[inline]
+    pub fn update_hash(&self, hash: u64, board: &BitBoard, mov: u64) -> u64 {
+        let index = Move::new(mov).get_board_index().get_index();
+        hash ^ self.field_value(index, board.number_of_stones().is_multiple_of(2))
+    }
+
+    /// Plays the field like BitBoard::play_field and updates the hash of the board alongside
+    #[inline]
+    pub fn play_field(&self, hash: u64, board: &BitBoard, mov: u64) -> (BitBoard, u64) {
+        (board.play_field(mov), self.update_hash(hash, board, mov))
     }
 
-    /// Updates the given zobrist hash and applies the move
-    /// by xoring the hash with the corresponding random value corresponding
-    /// to that position and player
     #[inline]
-    pub fn update_hash(&self, hash: u64, mov: u64, player: FieldType) -> u64 {
-        let leading_zeros = mov.leading_zeros() as usize;
-        //Todo: Is it faster to use arrays of length 8*7=56 in the zobrist tables
-        // to circumvent the padding correction in the update_hash() function?
-        // Con: 16 u64s of unnecessary space used
-        // Pro: Hot codepath has one division, one multiplication, one subtraction less
-
-        // Subtract the padding of the u64 board representation
-        // (two additional bits per column)
-        let column_count = leading_zeros / 6;
-        let index: usize = leading_zeros - column_count * 2;
-        return match player {
-            FieldType::Opponent => hash ^ self.table_p2[index],
-            FieldType::Player => hash ^ self.table_p1[index],
-        };
+    fn field_value(&self, index: u8, first: bool) -> u64 {
+        if first {
+            self.table_p1[index as usize]
+        } else {
+            self.table_p2[index as usize]
+        }
     }
 }
 
@@ -70,6 +88,7 @@ impl ZobristHasher {
 /// the indices of both vectors correspond to each other.
 /// Therefore, if a hash is found in the hash vector, the corresponding score can be found
 /// in the score vector at the same index.
+#[allow(dead_code)]
 struct TranspositionTable<T: Sized + Default + Clone> {
     ///Reserved size in number of entries. Does not correspond to the
     /// Number of stored entries, as the load factor will be kept below max_load_factor
@@ -93,6 +112,7 @@ struct TranspositionTable<T: Sized + Default + Clone> {
 }
 
 
+#[allow(dead_code)]
 impl<T: Clone + Default + Sized> TranspositionTable<T> {
     pub fn new(max_size: usize) -> TranspositionTable<T> {
         TranspositionTable {
@@ -155,7 +175,7 @@ impl<T: Clone + Default + Sized> TranspositionTable<T> {
 
     /// Returns the score for the given hash if it exists in the table
     pub fn get_mut(&mut self, hash: u64) -> Option<&mut T> {
-        let bucket = (hash % self.max_size as u64);
+        let bucket = hash % self.max_size as u64;
         let mut pos = bucket;
 
         let mut traversed = 0;
@@ -189,6 +209,47 @@ impl<T: Clone + Default + Sized> TranspositionTable<T> {
 mod tests {
     use super::*;
 
+    #[test]
+    pub fn test_zobrist_random_games() {
+        let hasher = ZobristHasher::new();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        for _ in 0..200 {
+            let mut board = BitBoard::empty();
+            let mut hash = hasher.hash_board(&board);
+            assert_eq!(hash, 0);
+
+            while !board.has_lost() && board.all_possible_moves() != 0 {
+                let column = rng.gen_range(0..7);
+                let mov = BitBoard::move_in_row(board.all_possible_moves(), column);
+                if mov == 0 {
+                    continue;
+                }
+                // Playing the move twice takes it back
+                assert_eq!(hasher.update_hash(hasher.update_hash(hash, &board, mov), &board, mov), hash);
+
+                let (new_board, new_hash) = hasher.play_field(hash, &board, mov);
+                assert_eq!(new_board, board.play_field(mov));
+                assert_eq!(new_hash, hasher.hash_board(&new_board));
+                assert_ne!(new_hash, hash);
+                board = new_board;
+                hash = new_hash;
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_zobrist_transpositions() {
+        let hasher = ZobristHasher::new();
+        let hash = |moves| hasher.hash_board(&BitBoard::from_moves(moves).unwrap());
+
+        assert_eq!(hash("4433"), hash("3344"));
+        assert_eq!(hash("12345"), hash("52341"));
+        // Same fields, but different colors
+        assert_ne!(hash("43"), hash("34"));
+        assert_ne!(hash("4"), hash("44"));
+    }
+
     #[test]
     pub fn test_insert() {
         let mut map = TranspositionTable::new(10);
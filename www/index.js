@@ -6,6 +6,8 @@ import {BitBoard, FieldType} from "../solver/pkg";
 const GRID_COLOR = "black";
 
 const canvas = document.getElementById("connect-four-canvas");
+const variantSelect = document.getElementById("variant");
+const misereCheckbox = document.getElementById("misere");
 const ctx = canvas.getContext("2d");
 
 // Width and height of Board
@@ -23,7 +25,12 @@ let GAME_STATE = FieldType.Opponent;
 canvas.width = (cellSize + borderWidth) * width + borderWidth
 canvas.height = (cellSize + borderWidth) * height + borderWidth
 
+// Late move reductions let the heuristic search reach its depth in the early game
+const searchOptions = new wasm.SearchOptions();
+searchOptions.late_move_reductions = true;
+
 let board = BitBoard.empty();
+// undefined while the game is running, null for a draw
 let winner = undefined;
 let last_guess = 3;
 // board = board.new_with_move(0, FieldType.Computer)
@@ -44,6 +51,11 @@ const checkWin = function() {
         console.log("Player won!")
         winner = FieldType.Opponent;
         return true;
+    } else if (board.number_of_stones() === width * height) {
+        // The solver can't search a full board
+        console.log("Draw!")
+        winner = null;
+        return true;
     } else {
         return false;
     }
@@ -66,8 +78,12 @@ const makeMove = function(row) {
         return;
     }
 
-    let b = board.play_column(row, FieldType.Opponent);
-    if (b === undefined) {
+    let b
+    try {
+        b = board.play_column(row, FieldType.Opponent);
+    } catch (error) {
+        // Full column
+        console.log(error)
         return
     }
 
@@ -85,13 +101,14 @@ const makeMove = function(row) {
 
     console.log("Number of stones: ", board.number_of_stones())
     let t1 = new Date().getTime();
-    if (board.number_of_stones() >= 15) {
+    // Only Connect Four is fast enough to be solved completely that early
+    if (board.line_length() === 4 && !board.is_misere() && board.number_of_stones() >= 15) {
         console.log("[Endgame] Solving Complete board")
         move = wasm.solve(board, 42, wasm.SolverType.Weak);
     }
     else {
         console.log("[Earlygame] Solving with heuristic score and depth: 15")
-        move = wasm.solve(board, 17, wasm.SolverType.Strong);
+        move = wasm.solve_with_options(board, 17, wasm.SolverType.Strong, searchOptions);
     }
     let t2 = new Date().getTime();
     console.log("Time: ", (t2-t1),"ms, Searched ", move.nodes_searched.toLocaleString(), " Nodes")
@@ -109,8 +126,9 @@ const makeMove = function(row) {
     } else {
         console.log("Player wins in ", move.end_in)
     }
-    // let b_new = board.new_with_move(move.move_row, FieldType.Opponent);
-    board.set_at(move.mov, FieldType.Player)
+    let b_new = board.play_column(move.column, FieldType.Player);
+    board.free()
+    board = b_new
 
     GAME_STATE = FieldType.Opponent;
 
@@ -173,6 +191,23 @@ canvas.addEventListener("click", event => {
     drawBoard();
 })
 
+/**
+ * Starts a new game of the selected variant, where the number of stones in a row win
+ * or, with misère rules, lose
+ */
+const newGame = function() {
+    board.free()
+    board = BitBoard.variant(Number(variantSelect.value), misereCheckbox.checked);
+    winner = undefined;
+    GAME_STATE = FieldType.Opponent;
+    ctx.clearRect(0, 0, canvas.width, canvas.height);
+    drawGrid();
+    drawBoard();
+}
+
+variantSelect.addEventListener("change", newGame);
+misereCheckbox.addEventListener("change", newGame);
+
 // makeMove(0);
 // makeMove(1);
 drawGrid();